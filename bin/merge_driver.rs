@@ -0,0 +1,125 @@
+//! Git merge driver for `.canvas` files.
+//!
+//! Register it with:
+//!
+//! ```text
+//! git config merge.jsoncanvas.name "JSON Canvas merge driver"
+//! git config merge.jsoncanvas.driver "canvas-merge-driver %O %A %B"
+//! echo "*.canvas merge=jsoncanvas" >> .gitattributes
+//! ```
+//!
+//! The merged canvas is written back to `%A`. When some changes could not be merged
+//! automatically, they are resolved according to `--policy` and `--delete`, a report
+//! is printed on stderr and the driver exits with status 1 so git flags the file as
+//! conflicted.
+
+use std::fs;
+use std::process::ExitCode;
+
+use jsoncanvas::io::SaveOptions;
+use jsoncanvas::merge::{ConflictPolicy, DeletePolicy, MergeOptions};
+use jsoncanvas::JsonCanvas;
+
+const USAGE: &str = "usage: canvas-merge-driver [--policy ours|theirs|base] [--delete keep|delete] [--report <file>] <base> <ours> <theirs>";
+
+struct Args {
+    options: MergeOptions,
+    report: Option<String>,
+    base: String,
+    ours: String,
+    theirs: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut options = MergeOptions::default();
+    let mut report = None;
+    let mut files = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--policy" => {
+                options.policy = match args.next().as_deref() {
+                    Some("ours") => ConflictPolicy::Ours,
+                    Some("theirs") => ConflictPolicy::Theirs,
+                    Some("base") => ConflictPolicy::Base,
+                    _ => return Err("--policy expects ours, theirs or base".to_string()),
+                }
+            }
+            "--delete" => {
+                options.delete_policy = match args.next().as_deref() {
+                    Some("keep") => DeletePolicy::Keep,
+                    Some("delete") => DeletePolicy::Delete,
+                    _ => return Err("--delete expects keep or delete".to_string()),
+                }
+            }
+            "--report" => report = Some(args.next().ok_or("--report expects a file")?),
+            // Files starting with a dash
+            "--" => files.extend(args.by_ref()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ => files.push(arg),
+        }
+    }
+
+    let [base, ours, theirs]: [String; 3] = files
+        .try_into()
+        .map_err(|_| "expected three files".to_string())?;
+    Ok(Args {
+        options,
+        report,
+        base,
+        ours,
+        theirs,
+    })
+}
+
+fn load(path: &str) -> Result<JsonCanvas, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    // An empty side means the file did not exist in that version
+    if content.trim().is_empty() {
+        return Ok(JsonCanvas::default());
+    }
+    content.parse().map_err(|e| format!("{}: {}", path, e))
+}
+
+fn run() -> Result<bool, String> {
+    let args = parse_args()?;
+    let base = load(&args.base)?;
+    let ours = load(&args.ours)?;
+    let theirs = load(&args.theirs)?;
+
+    let result = JsonCanvas::merge3(&base, &ours, &theirs, &args.options);
+    result
+        .canvas
+        .save(&args.ours, &SaveOptions::default())
+        .map_err(|e| e.to_string())?;
+
+    if result.is_clean() {
+        return Ok(true);
+    }
+
+    eprintln!(
+        "canvas-merge-driver: {} conflict(s)",
+        result.conflicts.len()
+    );
+    for conflict in &result.conflicts {
+        eprintln!("  {}", conflict);
+    }
+    if let Some(report) = args.report {
+        let json = serde_json::to_string_pretty(&result.conflicts).unwrap();
+        fs::write(&report, json).map_err(|e| format!("{}: {}", report, e))?;
+    }
+    Ok(false)
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("canvas-merge-driver: {}", e);
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
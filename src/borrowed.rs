@@ -0,0 +1,206 @@
+//! Read-only view of a canvas borrowing its strings from the parsed text.
+//!
+//! [`JsonCanvasRef::parse`] does not copy IDs, texts, paths or labels unless they
+//! contain escape sequences, which makes it cheaper than parsing a [`JsonCanvas`]
+//! when a large canvas is only inspected. Convert it with
+//! [`JsonCanvasRef::into_owned`] to modify it.
+
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+use crate::color::Color;
+use crate::edge::{Edge, End, Side};
+use crate::node::{optional_cow_str, NodeRef};
+use crate::parse;
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeRef<'a> {
+    #[serde(borrow)]
+    id: Cow<'a, str>,
+    #[serde(borrow)]
+    from_node: Cow<'a, str>,
+    from_side: Option<Side>,
+    from_end: Option<End>,
+    #[serde(borrow)]
+    to_node: Cow<'a, str>,
+    to_side: Option<Side>,
+    to_end: Option<End>,
+    color: Option<Color>,
+    #[serde(default, borrow, deserialize_with = "optional_cow_str")]
+    label: Option<Cow<'a, str>>,
+}
+
+impl EdgeRef<'_> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn from_node(&self) -> &str {
+        &self.from_node
+    }
+
+    pub fn from_side(&self) -> Option<Side> {
+        self.from_side
+    }
+
+    pub fn from_end(&self) -> Option<End> {
+        self.from_end
+    }
+
+    pub fn to_node(&self) -> &str {
+        &self.to_node
+    }
+
+    pub fn to_side(&self) -> Option<Side> {
+        self.to_side
+    }
+
+    pub fn to_end(&self) -> Option<End> {
+        self.to_end
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+impl From<EdgeRef<'_>> for Edge {
+    fn from(edge: EdgeRef<'_>) -> Self {
+        Edge::new(
+            EdgeId::new_unchecked(edge.id.into_owned()),
+            NodeId::new_unchecked(edge.from_node.into_owned()),
+            edge.from_side,
+            edge.from_end,
+            NodeId::new_unchecked(edge.to_node.into_owned()),
+            edge.to_side,
+            edge.to_end,
+            edge.color,
+            edge.label.map(Cow::into_owned),
+        )
+    }
+}
+
+/// Canvas borrowing from the text it was parsed from
+///
+/// ```
+/// use jsoncanvas::borrowed::JsonCanvasRef;
+/// use jsoncanvas::node::GenericNodeInfo;
+///
+/// let source = r#"{"nodes":[{"type":"text","id":"a","x":0,"y":0,"width":10,"height":10,"text":"A"}]}"#;
+/// let view = JsonCanvasRef::parse(source).unwrap();
+/// assert_eq!(view.get_node("a").unwrap().id(), "a");
+///
+/// let mut canvas = view.into_owned();
+/// canvas.get_node("a".parse().unwrap()).unwrap().set_x(5);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct JsonCanvasRef<'a> {
+    #[serde(default, borrow)]
+    nodes: Vec<NodeRef<'a>>,
+    #[serde(default, borrow)]
+    edges: Vec<EdgeRef<'a>>,
+}
+
+impl<'a> JsonCanvasRef<'a> {
+    pub fn parse(s: &'a str) -> Result<JsonCanvasRef<'a>, JsonCanvasError> {
+        match serde_json::from_str(s) {
+            Ok(canvas) => Ok(canvas),
+            // Same error as parsing a `JsonCanvas`, which tells where the problem is
+            Err(err) => Err(parse::parse(s).err().map_or_else(|| err.into(), Into::into)),
+        }
+    }
+
+    /// Nodes in z-order
+    pub fn nodes(&self) -> &[NodeRef<'a>] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[EdgeRef<'a>] {
+        &self.edges
+    }
+
+    pub fn get_node(&self, id: &str) -> Option<&NodeRef<'a>> {
+        self.nodes.iter().find(|node| node.id() == id)
+    }
+
+    pub fn get_edge(&self, id: &str) -> Option<&EdgeRef<'a>> {
+        self.edges.iter().find(|edge| edge.id() == id)
+    }
+
+    /// Copies the strings, so that the canvas can be modified
+    pub fn into_owned(self) -> JsonCanvas {
+        self.into()
+    }
+}
+
+impl From<JsonCanvasRef<'_>> for JsonCanvas {
+    fn from(canvas: JsonCanvasRef<'_>) -> Self {
+        let mut owned = JsonCanvas::default();
+        let nodes = owned.unobserved_nodes_mut();
+        for node in canvas.nodes {
+            let id = NodeId::new_unchecked(node.id().to_string());
+            nodes.insert(id, node.into());
+        }
+        let edges = owned.unobserved_edges_mut();
+        for edge in canvas.edges {
+            let id = EdgeId::new_unchecked(edge.id().to_string());
+            edges.insert(id, edge.into());
+        }
+        owned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r##"{"nodes":[{"type":"text","id":"a","x":0.5,"y":0,"width":10,"height":10,"text":"line\nbreak"},{"type":"file","id":"b","x":0,"y":0,"width":10,"height":10,"file":"f.md","subpath":"#h"},{"type":"link","id":"c","x":0,"y":0,"width":10,"height":10,"url":"https://example.com/"},{"type":"group","id":"d","x":0,"y":0,"width":10,"height":10,"label":"G","background":"bg.png","backgroundStyle":"cover"}],"edges":[{"id":"e","fromNode":"a","toNode":"b","toEnd":"arrow","label":"to"}]}"##;
+
+    fn borrowed(s: &str) -> bool {
+        SOURCE.as_bytes().as_ptr_range().contains(&s.as_ptr())
+    }
+
+    #[test]
+    fn borrows_unescaped_strings() {
+        let view = JsonCanvasRef::parse(SOURCE).unwrap();
+        let NodeRef::Text(text) = &view.nodes()[0] else {
+            panic!("expected a text node");
+        };
+        assert!(borrowed(view.nodes()[0].id()));
+        assert!(!borrowed(text.text()));
+        assert_eq!(text.text(), "line\nbreak");
+        let NodeRef::Group(group) = view.get_node("d").unwrap() else {
+            panic!("expected a group node");
+        };
+        assert!(borrowed(group.label().unwrap()));
+        let NodeRef::File(file) = view.get_node("b").unwrap() else {
+            panic!("expected a file node");
+        };
+        assert!(borrowed(file.subpath().unwrap()));
+        assert_eq!(group.background().unwrap().image(), "bg.png");
+        let edge = view.get_edge("e").unwrap();
+        assert!(borrowed(edge.label().unwrap()));
+        assert_eq!(edge.to_end(), Some(End::Arrow));
+    }
+
+    #[test]
+    fn converts_to_owned() {
+        let owned = JsonCanvasRef::parse(SOURCE).unwrap().into_owned();
+        assert_eq!(owned, SOURCE.parse::<JsonCanvas>().unwrap());
+        assert_eq!(owned.to_string(), SOURCE);
+
+        let invalid = SOURCE.replace("https://example.com/", "not a url");
+        let err = JsonCanvasRef::parse(&invalid).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            invalid.parse::<JsonCanvas>().unwrap_err().to_string()
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use crate::import::{IdMapping, IdPolicy};
+use crate::node::GenericNodeInfo;
+use crate::{JsonCanvas, JsonCanvasError, NodeId, PixelCoordinate};
+
+impl JsonCanvas {
+    /// Copies the selected nodes into a new canvas, along with the edges connecting
+    /// two of them.
+    ///
+    /// With `include_group_content`, the nodes lying inside a selected group are
+    /// copied too. Nodes and edges keep their IDs and their order.
+    pub fn extract(
+        &self,
+        selection: &[NodeId],
+        include_group_content: bool,
+    ) -> Result<JsonCanvas, JsonCanvasError> {
+        let mut selected: HashSet<NodeId> = HashSet::new();
+        for id in selection {
+            if !self.get_nodes().contains_key(id) {
+                return Err(JsonCanvasError::NodeNotExists(id.clone()));
+            }
+            selected.insert(id.clone());
+            if include_group_content {
+                selected.extend(self.group_content(id));
+            }
+        }
+
+        let mut fragment = JsonCanvas::default();
+        for node in self.nodes() {
+            if selected.contains(node.id()) {
+                fragment.add_node(node.clone())?;
+            }
+        }
+        for edge in self.edges() {
+            if selected.contains(edge.from_node()) && selected.contains(edge.to_node()) {
+                fragment.add_edge(edge.clone())?;
+            }
+        }
+        Ok(fragment)
+    }
+
+    /// Inserts a copy of `fragment` with its top-left corner at `at`.
+    ///
+    /// Every node and edge gets a new ID, so the same fragment can be pasted several
+    /// times.
+    pub fn paste(
+        &mut self,
+        fragment: &JsonCanvas,
+        at: (PixelCoordinate, PixelCoordinate),
+    ) -> Result<IdMapping, JsonCanvasError> {
+        let offset = match fragment.bounds() {
+            Some(bounds) => (at.0 - bounds.x, at.1 - bounds.y),
+            None => (0, 0),
+        };
+        self.import(fragment.clone(), offset, IdPolicy::RegenerateAll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+    use crate::geometry::Rect;
+    use crate::node::{GenericNodeInfo, GroupNode, TextNode};
+
+    fn edge(id: &str, from: &str, to: &str) -> Edge {
+        Edge::new(
+            id.parse().unwrap(),
+            from.parse().unwrap(),
+            None,
+            None,
+            to.parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn canvas() -> JsonCanvas {
+        let mut canvas = JsonCanvas::default();
+        canvas
+            .add_node(GroupNode::new("g".parse().unwrap(), 0, 0, 300, 300, None, None, None).into())
+            .unwrap();
+        for (id, x) in [("a", 10), ("b", 150), ("c", 500)] {
+            canvas
+                .add_node(
+                    TextNode::new(id.parse().unwrap(), x, 10, 100, 100, None, id.to_string())
+                        .into(),
+                )
+                .unwrap();
+        }
+        canvas.add_edge(edge("ab", "a", "b")).unwrap();
+        canvas.add_edge(edge("bc", "b", "c")).unwrap();
+        canvas
+    }
+
+    #[test]
+    fn extract_group_with_content() {
+        let fragment = canvas().extract(&["g".parse().unwrap()], true).unwrap();
+        let ids: Vec<&str> = fragment.nodes().map(|node| node.id().as_str()).collect();
+        assert_eq!(ids, vec!["g", "a", "b"]);
+        assert_eq!(fragment.get_edges().len(), 1);
+
+        let fragment = canvas().extract(&["g".parse().unwrap()], false).unwrap();
+        assert_eq!(fragment.get_nodes().len(), 1);
+        assert!(fragment.get_edges().is_empty());
+    }
+
+    #[test]
+    fn paste_with_fresh_ids() {
+        let mut canvas = canvas();
+        let fragment = canvas
+            .extract(&["a".parse().unwrap(), "b".parse().unwrap()], false)
+            .unwrap();
+
+        let mapping = canvas.paste(&fragment, (1000, 1000)).unwrap();
+        canvas.paste(&fragment, (2000, 1000)).unwrap();
+
+        assert_eq!(canvas.get_nodes().len(), 8);
+        assert_eq!(canvas.get_edges().len(), 4);
+        let a = &canvas.get_nodes()[&mapping.nodes[&"a".parse::<NodeId>().unwrap()]];
+        let b = &canvas.get_nodes()[&mapping.nodes[&"b".parse::<NodeId>().unwrap()]];
+        assert_eq!((a.get_x(), a.get_y()), (1000, 1000));
+        assert_eq!(Rect::of(b), Rect::new(1140, 1000, 100, 100));
+    }
+}
@@ -1,7 +1,8 @@
 pub use hex_color::HexColor;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum PresetColor {
     #[serde(rename = "1")]
     Red = 1,
@@ -17,11 +18,53 @@ pub enum PresetColor {
     Purple = 6,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+impl PresetColor {
+    pub const ALL: [PresetColor; 6] = [
+        PresetColor::Red,
+        PresetColor::Orange,
+        PresetColor::Yellow,
+        PresetColor::Green,
+        PresetColor::Cyan,
+        PresetColor::Purple,
+    ];
+
+    /// Color used by Obsidian to display the preset
+    pub fn rgb(self) -> HexColor {
+        match self {
+            PresetColor::Red => HexColor::rgb(0xfb, 0x46, 0x4c),
+            PresetColor::Orange => HexColor::rgb(0xe9, 0x97, 0x3f),
+            PresetColor::Yellow => HexColor::rgb(0xe0, 0xde, 0x71),
+            PresetColor::Green => HexColor::rgb(0x44, 0xcf, 0x6e),
+            PresetColor::Cyan => HexColor::rgb(0x53, 0xdf, 0xdd),
+            PresetColor::Purple => HexColor::rgb(0xa8, 0x82, 0xff),
+        }
+    }
+
+    /// Preset closest to `color`, by distance between their RGB values
+    pub fn nearest(color: HexColor) -> PresetColor {
+        let distance = |preset: &PresetColor| {
+            let rgb = preset.rgb();
+            [(rgb.r, color.r), (rgb.g, color.g), (rgb.b, color.b)]
+                .into_iter()
+                .map(|(a, b)| (a as i32 - b as i32).pow(2))
+                .sum::<i32>()
+        };
+        PresetColor::ALL.into_iter().min_by_key(distance).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(untagged)]
 pub enum Color {
     Preset(PresetColor),
-    Color(HexColor),
+    /// `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA`
+    Color(
+        #[schemars(
+            with = "String",
+            regex(pattern = "^#([0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$")
+        )]
+        HexColor,
+    ),
 }
 
 impl From<PresetColor> for Color {
@@ -58,6 +101,18 @@ mod tests {
         assert_eq!(color, Color::Color(HexColor::rgb(255, 0, 0)))
     }
 
+    #[test]
+    fn nearest_preset() {
+        assert_eq!(
+            PresetColor::nearest(HexColor::rgb(255, 0, 0)),
+            PresetColor::Red
+        );
+        assert_eq!(
+            PresetColor::nearest(HexColor::rgb(0, 0, 255)),
+            PresetColor::Purple
+        );
+    }
+
     #[test]
     fn color_ser() {
         assert_eq!(
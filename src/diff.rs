@@ -0,0 +1,547 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+use serde::{Serialize, Serializer};
+
+use crate::color::Color;
+use crate::edge::{Edge, End, Side};
+use crate::node::{GenericNodeInfo, Node};
+use crate::{EdgeId, JsonCanvas, NodeId};
+
+/// One end of an edge, as compared by [`diff`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Endpoint {
+    pub node: NodeId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub side: Option<Side>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<End>,
+}
+
+/// A single difference between two canvases
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "camelCase")]
+pub enum Change {
+    NodeAdded {
+        id: NodeId,
+    },
+    NodeRemoved {
+        id: NodeId,
+    },
+    /// Exact positions, which may be floats read from a file
+    NodeMoved {
+        id: NodeId,
+        #[serde(serialize_with = "numbers")]
+        from: (f64, f64),
+        #[serde(serialize_with = "numbers")]
+        to: (f64, f64),
+    },
+    /// Exact sizes, which may be floats read from a file
+    NodeResized {
+        id: NodeId,
+        #[serde(serialize_with = "numbers")]
+        from: (f64, f64),
+        #[serde(serialize_with = "numbers")]
+        to: (f64, f64),
+    },
+    NodeRecolored {
+        id: NodeId,
+        from: Option<Color>,
+        to: Option<Color>,
+    },
+    NodeTextChanged {
+        id: NodeId,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// Type, file, URL or background of the node changed
+    NodeContentChanged {
+        id: NodeId,
+    },
+    EdgeAdded {
+        id: EdgeId,
+    },
+    EdgeRemoved {
+        id: EdgeId,
+    },
+    EdgeEndpointsChanged {
+        id: EdgeId,
+        from: (Endpoint, Endpoint),
+        to: (Endpoint, Endpoint),
+    },
+    EdgeRecolored {
+        id: EdgeId,
+        from: Option<Color>,
+        to: Option<Color>,
+    },
+    EdgeLabelChanged {
+        id: EdgeId,
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+/// Structured differences between two canvases.
+///
+/// Changes are sorted by node ID, then by edge ID, so the result does not depend
+/// on the order of the nodes and edges in the files. Serializing it gives a
+/// machine-readable JSON list, and `Display` gives a human-readable summary.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+#[serde(transparent)]
+pub struct CanvasDiff {
+    changes: Vec<Change>,
+}
+
+impl CanvasDiff {
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+}
+
+impl IntoIterator for CanvasDiff {
+    type Item = Change;
+    type IntoIter = std::vec::IntoIter<Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+/// Computes the changes turning canvas `a` into canvas `b`
+pub fn diff(a: &JsonCanvas, b: &JsonCanvas) -> CanvasDiff {
+    let mut changes = Vec::new();
+
+    let node_ids: BTreeSet<&NodeId> = a.get_nodes().keys().chain(b.get_nodes().keys()).collect();
+    for id in node_ids {
+        match (a.get_nodes().get(id), b.get_nodes().get(id)) {
+            (None, Some(_)) => changes.push(Change::NodeAdded { id: id.clone() }),
+            (Some(_), None) => changes.push(Change::NodeRemoved { id: id.clone() }),
+            (Some(old), Some(new)) => diff_node(old, new, &mut changes),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    let edge_ids: BTreeSet<&EdgeId> = a.get_edges().keys().chain(b.get_edges().keys()).collect();
+    for id in edge_ids {
+        match (a.get_edges().get(id), b.get_edges().get(id)) {
+            (None, Some(_)) => changes.push(Change::EdgeAdded { id: id.clone() }),
+            (Some(_), None) => changes.push(Change::EdgeRemoved { id: id.clone() }),
+            (Some(old), Some(new)) => diff_edge(old, new, &mut changes),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    CanvasDiff { changes }
+}
+
+fn diff_node(old: &Node, new: &Node, changes: &mut Vec<Change>) {
+    let id = new.id();
+    let (from, to) = (
+        (old.get_exact_x(), old.get_exact_y()),
+        (new.get_exact_x(), new.get_exact_y()),
+    );
+    if from != to {
+        changes.push(Change::NodeMoved {
+            id: id.clone(),
+            from,
+            to,
+        });
+    }
+
+    let (from, to) = (
+        (old.get_exact_width(), old.get_exact_height()),
+        (new.get_exact_width(), new.get_exact_height()),
+    );
+    if from != to {
+        changes.push(Change::NodeResized {
+            id: id.clone(),
+            from,
+            to,
+        });
+    }
+
+    if old.color() != new.color() {
+        changes.push(Change::NodeRecolored {
+            id: id.clone(),
+            from: *old.color(),
+            to: *new.color(),
+        });
+    }
+
+    let (from, to) = (text(old), text(new));
+    if from != to {
+        changes.push(Change::NodeTextChanged {
+            id: id.clone(),
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+        });
+    }
+
+    if !same_content(old, new) {
+        changes.push(Change::NodeContentChanged { id: id.clone() });
+    }
+}
+
+fn diff_edge(old: &Edge, new: &Edge, changes: &mut Vec<Change>) {
+    let id = new.id();
+    let (from, to) = (endpoints(old), endpoints(new));
+    if from != to {
+        changes.push(Change::EdgeEndpointsChanged {
+            id: id.clone(),
+            from,
+            to,
+        });
+    }
+
+    if old.color() != new.color() {
+        changes.push(Change::EdgeRecolored {
+            id: id.clone(),
+            from: old.color().copied(),
+            to: new.color().copied(),
+        });
+    }
+
+    if old.label() != new.label() {
+        changes.push(Change::EdgeLabelChanged {
+            id: id.clone(),
+            from: old.label().cloned(),
+            to: new.label().cloned(),
+        });
+    }
+}
+
+/// Text displayed by the node: the text of a text node, or the label of a group
+fn text(node: &Node) -> Option<&str> {
+    match node {
+        Node::Text(node) => Some(node.text()),
+        Node::Group(node) => node.label().map(String::as_str),
+        Node::File(_) | Node::Link(_) => None,
+    }
+}
+
+fn same_content(old: &Node, new: &Node) -> bool {
+    match (old, new) {
+        (Node::Text(_), Node::Text(_)) => true,
+        (Node::File(old), Node::File(new)) => {
+            old.file() == new.file() && old.subpath() == new.subpath()
+        }
+        (Node::Link(old), Node::Link(new)) => old.url() == new.url(),
+        (Node::Group(old), Node::Group(new)) => old.background() == new.background(),
+        _ => false,
+    }
+}
+
+fn endpoints(edge: &Edge) -> (Endpoint, Endpoint) {
+    (
+        Endpoint {
+            node: edge.from_node().clone(),
+            side: edge.from_side().copied(),
+            end: edge.from_end().copied(),
+        },
+        Endpoint {
+            node: edge.to_node().clone(),
+            side: edge.to_side().copied(),
+            end: edge.to_end().copied(),
+        },
+    )
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.node)?;
+        if let Some(side) = &self.side {
+            write!(
+                f,
+                ":{}",
+                serde_json::to_value(side).unwrap().as_str().unwrap()
+            )?;
+        }
+        if let Some(end) = &self.end {
+            write!(
+                f,
+                " ({})",
+                serde_json::to_value(end).unwrap().as_str().unwrap()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes whole numbers as integers, as they are in the canvas
+fn numbers<S: Serializer>(pair: &(f64, f64), serializer: S) -> Result<S::Ok, S::Error> {
+    let number = |value: f64| {
+        if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+            serde_json::Value::from(value as i64)
+        } else {
+            serde_json::Value::from(value)
+        }
+    };
+    (number(pair.0), number(pair.1)).serialize(serializer)
+}
+
+fn color_str(color: &Option<Color>) -> String {
+    match color {
+        Some(color) => serde_json::to_value(color)
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string(),
+        None => "none".to_string(),
+    }
+}
+
+fn text_str(text: &Option<String>) -> String {
+    match text {
+        Some(text) => format!("{:?}", text),
+        None => "none".to_string(),
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::NodeAdded { id } => write!(f, "+ node {}", id),
+            Change::NodeRemoved { id } => write!(f, "- node {}", id),
+            Change::NodeMoved { id, from, to } => write!(
+                f,
+                "~ node {} moved from ({}, {}) to ({}, {})",
+                id, from.0, from.1, to.0, to.1
+            ),
+            Change::NodeResized { id, from, to } => write!(
+                f,
+                "~ node {} resized from {}x{} to {}x{}",
+                id, from.0, from.1, to.0, to.1
+            ),
+            Change::NodeRecolored { id, from, to } => write!(
+                f,
+                "~ node {} recolored from {} to {}",
+                id,
+                color_str(from),
+                color_str(to)
+            ),
+            Change::NodeTextChanged { id, from, to } => write!(
+                f,
+                "~ node {} text changed from {} to {}",
+                id,
+                text_str(from),
+                text_str(to)
+            ),
+            Change::NodeContentChanged { id } => write!(f, "~ node {} content changed", id),
+            Change::EdgeAdded { id } => write!(f, "+ edge {}", id),
+            Change::EdgeRemoved { id } => write!(f, "- edge {}", id),
+            Change::EdgeEndpointsChanged { id, from, to } => write!(
+                f,
+                "~ edge {} reconnected from {} -> {} to {} -> {}",
+                id, from.0, from.1, to.0, to.1
+            ),
+            Change::EdgeRecolored { id, from, to } => write!(
+                f,
+                "~ edge {} recolored from {} to {}",
+                id,
+                color_str(from),
+                color_str(to)
+            ),
+            Change::EdgeLabelChanged { id, from, to } => write!(
+                f,
+                "~ edge {} label changed from {} to {}",
+                id,
+                text_str(from),
+                text_str(to)
+            ),
+        }
+    }
+}
+
+impl Display for CanvasDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::PresetColor;
+    use crate::node::TextNode;
+    use crate::patch::CanvasPatch;
+    use crate::PixelCoordinate;
+
+    fn canvas(x: PixelCoordinate, text: &str, color: Option<Color>) -> JsonCanvas {
+        let mut canvas = JsonCanvas::default();
+        canvas
+            .add_node(
+                TextNode::new(
+                    "a".parse().unwrap(),
+                    x,
+                    0,
+                    100,
+                    100,
+                    color,
+                    text.to_string(),
+                )
+                .into(),
+            )
+            .unwrap();
+        canvas
+            .add_node(
+                TextNode::new(
+                    "b".parse().unwrap(),
+                    0,
+                    200,
+                    100,
+                    100,
+                    None,
+                    "b".to_string(),
+                )
+                .into(),
+            )
+            .unwrap();
+        canvas
+    }
+
+    #[test]
+    fn identical_canvases() {
+        assert!(diff(&canvas(0, "a", None), &canvas(0, "a", None)).is_empty());
+    }
+
+    #[test]
+    fn node_changes() {
+        let mut new = canvas(10, "A", Some(PresetColor::Red.into()));
+        new.add_edge(Edge::new(
+            "e".parse().unwrap(),
+            "a".parse().unwrap(),
+            None,
+            None,
+            "b".parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+
+        let changes = diff(&canvas(0, "a", None), &new);
+        assert_eq!(
+            changes.to_string(),
+            "~ node a moved from (0, 0) to (10, 0)\n\
+             ~ node a recolored from none to 1\n\
+             ~ node a text changed from \"a\" to \"A\"\n\
+             + edge e\n"
+        );
+        assert_eq!(
+            serde_json::to_value(&changes).unwrap()[0],
+            serde_json::json!({"change": "nodeMoved", "id": "a", "from": [0, 0], "to": [10, 0]})
+        );
+    }
+
+    #[test]
+    fn float_coordinates() {
+        let node = |x: &str, width: &str| {
+            format!(
+                r#"{{"nodes":[{{"type":"text","id":"a","x":{},"y":0,"width":{},"height":10,"text":"a"}}]}}"#,
+                x, width
+            )
+        };
+        let a: JsonCanvas = node("10.2", "100").parse().unwrap();
+        let b: JsonCanvas = node("10.4", "100.5").parse().unwrap();
+
+        let changes = diff(&a, &b);
+        assert_eq!(
+            changes.to_string(),
+            "~ node a moved from (10.2, 0) to (10.4, 0)\n\
+             ~ node a resized from 100x10 to 100.5x10\n"
+        );
+        assert_eq!(
+            serde_json::to_value(&changes).unwrap()[1],
+            serde_json::json!({"change": "nodeResized", "id": "a", "from": [100, 10], "to": [100.5, 10]})
+        );
+
+        let mut patched = a.clone();
+        patched.apply(&CanvasPatch::between(&a, &b)).unwrap();
+        assert_eq!(patched, b);
+        assert_eq!(patched.to_string(), b.to_string());
+    }
+
+    fn edge(id: &str, from: &str, to: &str) -> Edge {
+        Edge::new(
+            id.parse().unwrap(),
+            from.parse().unwrap(),
+            None,
+            None,
+            to.parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn edge_changes_and_removals() {
+        let mut old = canvas(0, "a", None);
+        old.add_node(
+            TextNode::new(
+                "c".parse().unwrap(),
+                300,
+                0,
+                100,
+                100,
+                None,
+                "c".to_string(),
+            )
+            .into(),
+        )
+        .unwrap();
+        old.add_edge(edge("e", "a", "b")).unwrap();
+        old.add_edge(edge("f", "a", "b")).unwrap();
+        old.add_edge(edge("g", "b", "a")).unwrap();
+
+        let mut new = canvas(0, "a", None);
+        new.node_mut(&"a".parse().unwrap()).unwrap().set_width(150);
+        let mut e = edge("e", "a", "b");
+        e.set_from("a".parse().unwrap(), Some(Side::Right), None);
+        e.set_to("b".parse().unwrap(), Some(Side::Left), Some(End::None));
+        new.add_edge(e).unwrap();
+        let mut g = edge("g", "a", "b");
+        g.set_label("back".to_string());
+        new.add_edge(g).unwrap();
+
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes.to_string(),
+            "~ node a resized from 100x100 to 150x100\n\
+             - node c\n\
+             ~ edge e reconnected from a -> b to a:right -> b:left (none)\n\
+             - edge f\n\
+             ~ edge g reconnected from b -> a to a -> b\n\
+             ~ edge g label changed from none to \"back\"\n"
+        );
+
+        // Only the arrow changed
+        let mut arrow = new.clone();
+        arrow.edge_mut(&"e".parse().unwrap()).unwrap().set_to(
+            "b".parse().unwrap(),
+            Some(Side::Left),
+            Some(End::Arrow),
+        );
+        assert_eq!(
+            diff(&new, &arrow).to_string(),
+            "~ edge e reconnected from a:right -> b:left (none) to a:right -> b:left (arrow)\n"
+        );
+
+        let mut patched = old.clone();
+        patched.apply(&CanvasPatch::between(&old, &new)).unwrap();
+        assert_eq!(patched, new);
+    }
+}
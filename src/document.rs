@@ -0,0 +1,527 @@
+//! Editing of canvas files which keeps their original formatting.
+//!
+//! A [`CanvasDocument`] remembers the text it was parsed from. When written back,
+//! only the nodes, edges and fields which changed are rewritten: everything else,
+//! including whitespace, key order and unknown fields, is copied from the original
+//! text.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::node::GenericNodeInfo;
+use crate::parse::Scanner;
+use crate::{JsonCanvas, JsonCanvasError};
+
+/// Canvas parsed from a file, which can be edited and written back with minimal
+/// changes to the text
+///
+/// ```
+/// use jsoncanvas::color::{Color, PresetColor};
+/// use jsoncanvas::document::CanvasDocument;
+///
+/// let source = "{\n  \"nodes\": [\n    {\"type\": \"text\", \"id\": \"a\", \"x\": 0, \"y\": 0,\n     \"width\": 10, \"height\": 10, \"text\": \"A\"}\n  ]\n}";
+/// let mut document: CanvasDocument = source.parse().unwrap();
+/// document
+///     .canvas_mut()
+///     .get_node("a".parse().unwrap())
+///     .unwrap()
+///     .set_color(Some(Color::Preset(PresetColor::Red)));
+/// assert_eq!(
+///     document.to_string(),
+///     "{\n  \"nodes\": [\n    {\"type\": \"text\", \"id\": \"a\", \"x\": 0, \"y\": 0,\n     \"width\": 10, \"height\": 10, \"text\": \"A\", \"color\": \"1\"}\n  ]\n}"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct CanvasDocument {
+    source: String,
+    original: JsonCanvas,
+    canvas: JsonCanvas,
+}
+
+impl CanvasDocument {
+    pub fn canvas(&self) -> &JsonCanvas {
+        &self.canvas
+    }
+
+    pub fn canvas_mut(&mut self) -> &mut JsonCanvas {
+        &mut self.canvas
+    }
+
+    /// Text the document was parsed from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn into_canvas(self) -> JsonCanvas {
+        self.canvas
+    }
+
+    /// Whether the canvas was changed since it was parsed, including the order of
+    /// its nodes and edges, which comparing canvases ignores
+    pub fn is_modified(&self) -> bool {
+        self.canvas != self.original
+            || !self
+                .canvas
+                .nodes()
+                .map(|node| node.id())
+                .eq(self.original.nodes().map(|node| node.id()))
+            || !self
+                .canvas
+                .edges()
+                .map(|edge| edge.id())
+                .eq(self.original.edges().map(|edge| edge.id()))
+    }
+}
+
+impl FromStr for CanvasDocument {
+    type Err = JsonCanvasError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let canvas: JsonCanvas = s.parse()?;
+        Ok(CanvasDocument {
+            source: s.to_string(),
+            original: canvas.clone(),
+            canvas,
+        })
+    }
+}
+
+impl Display for CanvasDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.is_modified() {
+            return f.write_str(&self.source);
+        }
+        match self.render() {
+            Some(text) => f.write_str(&text),
+            None => write!(f, "{}", self.canvas),
+        }
+    }
+}
+
+/// Members of a JSON object or array in the source text
+struct Members {
+    /// Position right after the opening bracket
+    open: usize,
+    /// Position of the closing bracket
+    close: usize,
+    /// Each member, from the key of an object member to the end of its value
+    spans: Vec<Range<usize>>,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    key: String,
+    /// From the end of the key to the start of the value, like `": "`
+    colon: Range<usize>,
+    value: Range<usize>,
+}
+
+fn scan(text: &str, start: usize) -> Option<Members> {
+    let mut scanner = Scanner {
+        text: text.as_bytes(),
+        pos: start,
+    };
+    scanner.skip_whitespace();
+    let object = match scanner.peek()? {
+        b'{' => true,
+        b'[' => false,
+        _ => return None,
+    };
+    scanner.pos += 1;
+    let open = scanner.pos;
+    let mut members = Members {
+        open,
+        close: open,
+        spans: Vec::new(),
+        fields: Vec::new(),
+    };
+    scanner.skip_whitespace();
+    if !matches!(scanner.peek()?, b'}' | b']') {
+        loop {
+            scanner.skip_whitespace();
+            let start = scanner.pos;
+            let key = if object {
+                let key = scanner.key()?;
+                let key_end = scanner.pos;
+                scanner.expect(b':')?;
+                Some((key, key_end))
+            } else {
+                None
+            };
+            scanner.skip_whitespace();
+            let value_start = scanner.pos;
+            scanner.skip_value()?;
+            members.spans.push(start..scanner.pos);
+            if let Some((key, key_end)) = key {
+                members.fields.push(Field {
+                    key,
+                    colon: key_end..value_start,
+                    value: value_start..scanner.pos,
+                });
+            }
+            if scanner.expect(b',').is_none() {
+                break;
+            }
+        }
+    }
+    scanner.skip_whitespace();
+    members.close = scanner.pos;
+    matches!(scanner.peek()?, b'}' | b']').then_some(members)
+}
+
+/// Member of an edited object or array
+enum Piece {
+    /// Member at this index in the source, with its new text
+    Kept(usize, String),
+    New(String),
+}
+
+impl Members {
+    /// Rebuilds the members between the brackets. Whitespace around a member and
+    /// separators between members which stay next to each other are kept.
+    fn rebuild(&self, text: &str, pieces: Vec<Piece>) -> String {
+        if pieces.is_empty() {
+            return String::new();
+        }
+        let Some(first) = self.spans.first() else {
+            let texts: Vec<String> = pieces
+                .into_iter()
+                .map(|piece| match piece {
+                    Piece::Kept(_, text) | Piece::New(text) => text,
+                })
+                .collect();
+            return texts.join(",");
+        };
+        let leading = &text[self.open..first.start];
+        let trailing = &text[self.spans[self.spans.len() - 1].end..self.close];
+        let separator = match self.spans.get(1) {
+            Some(second) => text[first.end..second.start].to_string(),
+            None => format!(",{}", leading),
+        };
+
+        let mut out = leading.to_string();
+        let mut previous: Option<usize> = None;
+        for (position, piece) in pieces.into_iter().enumerate() {
+            let (index, piece) = match piece {
+                Piece::Kept(index, text) => (Some(index), text),
+                Piece::New(text) => (None, text),
+            };
+            if position > 0 {
+                match (previous, index) {
+                    (Some(previous), Some(index)) if index == previous + 1 => {
+                        out.push_str(&text[self.spans[previous].end..self.spans[index].start])
+                    }
+                    _ => out.push_str(&separator),
+                }
+            }
+            out.push_str(&piece);
+            previous = index;
+        }
+        out.push_str(trailing);
+        out
+    }
+
+    /// Separator between the key and the value of the first member, like `": "`
+    fn colon<'a>(&self, text: &'a str) -> &'a str {
+        match self.fields.first() {
+            Some(field) => &text[field.colon.clone()],
+            None => ":",
+        }
+    }
+}
+
+/// Node or edge of the canvas
+#[derive(PartialEq)]
+struct Item {
+    id: String,
+    fields: Map<String, Value>,
+}
+
+impl Item {
+    fn new(id: String, value: &impl Serialize) -> Item {
+        let fields = match serde_json::to_value(value) {
+            Ok(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+        Item { id, fields }
+    }
+}
+
+fn field(colon: &str, key: &str, value: &Value) -> String {
+    format!("{}{}{}", Value::from(key), colon, value)
+}
+
+impl CanvasDocument {
+    fn render(&self) -> Option<String> {
+        let text = self.source.as_str();
+        let root = scan(text, 0)?;
+        let lists = [
+            (
+                "nodes",
+                items(self.original.nodes(), |n| n.id().to_string()),
+                items(self.canvas.nodes(), |n| n.id().to_string()),
+            ),
+            (
+                "edges",
+                items(self.original.edges(), |e| e.id().to_string()),
+                items(self.canvas.edges(), |e| e.id().to_string()),
+            ),
+        ];
+
+        let mut pieces: Vec<Piece> = root
+            .spans
+            .iter()
+            .enumerate()
+            .map(|(index, span)| Piece::Kept(index, text[span.clone()].to_string()))
+            .collect();
+        for (key, old, new) in lists {
+            if old == new {
+                continue;
+            }
+            match root.fields.iter().position(|field| field.key == key) {
+                Some(index) => {
+                    let span = root.spans[index].clone();
+                    let members = scan(text, root.fields[index].value.start)?;
+                    let content = self.rebuild_list(&members, &old, new)?;
+                    pieces[index] = Piece::Kept(
+                        index,
+                        format!(
+                            "{}{}{}",
+                            &text[span.start..members.open],
+                            content,
+                            &text[members.close..span.end]
+                        ),
+                    );
+                }
+                None if !new.is_empty() => {
+                    let items: Vec<String> = new.into_iter().map(|(_, json)| json).collect();
+                    let list = format!("[{}]", items.join(","));
+                    pieces.push(Piece::New(format!(
+                        "{}{}{}",
+                        Value::from(key),
+                        root.colon(text),
+                        list
+                    )));
+                }
+                None => {}
+            }
+        }
+        Some(format!(
+            "{}{}{}",
+            &text[..root.open],
+            root.rebuild(text, pieces),
+            &text[root.close..]
+        ))
+    }
+
+    /// New content of the `nodes` or `edges` array
+    fn rebuild_list(
+        &self,
+        members: &Members,
+        old: &[(Item, String)],
+        new: Vec<(Item, String)>,
+    ) -> Option<String> {
+        let text = self.source.as_str();
+        let old: HashMap<&str, &Map<String, Value>> = old
+            .iter()
+            .map(|(item, _)| (item.id.as_str(), &item.fields))
+            .collect();
+        // First member of the array for each ID
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (index, span) in members.spans.iter().enumerate() {
+            let item = scan(text, span.start)?;
+            let id = item
+                .fields
+                .iter()
+                .find(|field| field.key == "id")
+                .and_then(|field| serde_json::from_str::<String>(&text[field.value.clone()]).ok());
+            if let Some(id) = id {
+                positions.entry(id).or_insert(index);
+            }
+        }
+
+        let mut pieces = Vec::new();
+        for (item, json) in new {
+            match (positions.get(&item.id), old.get(item.id.as_str())) {
+                (Some(&index), Some(&previous)) => {
+                    let span = members.spans[index].clone();
+                    let piece = if *previous == item.fields {
+                        text[span].to_string()
+                    } else {
+                        let members = scan(text, span.start)?;
+                        let content = edit_item(text, &members, previous, &item.fields);
+                        format!(
+                            "{}{}{}",
+                            &text[span.start..members.open],
+                            content,
+                            &text[members.close..span.end]
+                        )
+                    };
+                    pieces.push(Piece::Kept(index, piece));
+                }
+                _ => pieces.push(Piece::New(json)),
+            }
+        }
+        Some(members.rebuild(text, pieces))
+    }
+}
+
+/// New content of a node or edge, changing only the fields which differ between
+/// `old` and `new`
+fn edit_item(
+    text: &str,
+    members: &Members,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+) -> String {
+    let colon = members.colon(text);
+    let mut pieces = Vec::new();
+    for (index, Field { key, value, .. }) in members.fields.iter().enumerate() {
+        let span = members.spans[index].clone();
+        let known = old.contains_key(key) || new.contains_key(key);
+        match new.get(key) {
+            _ if !known => pieces.push(Piece::Kept(index, text[span].to_string())),
+            Some(new_value) if old.get(key) == Some(new_value) => {
+                pieces.push(Piece::Kept(index, text[span].to_string()))
+            }
+            Some(new_value) => pieces.push(Piece::Kept(
+                index,
+                format!("{}{}", &text[span.start..value.start], new_value),
+            )),
+            None => {}
+        }
+    }
+    for (key, value) in new {
+        if !members.fields.iter().any(|field| field.key == *key) {
+            pieces.push(Piece::New(field(colon, key, value)));
+        }
+    }
+    members.rebuild(text, pieces)
+}
+
+/// Nodes or edges of the canvas, with their JSON as written by [`JsonCanvas`]
+fn items<'a, T: Serialize + 'a>(
+    values: impl Iterator<Item = &'a T>,
+    id: impl Fn(&T) -> String,
+) -> Vec<(Item, String)> {
+    values
+        .map(|value| {
+            let json = serde_json::to_string(value).unwrap_or_default();
+            (Item::new(id(value), value), json)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{Color, PresetColor};
+    use crate::edge::Edge;
+    use crate::node::{Node, TextNode};
+
+    const SOURCE: &str = "{\n\t\"nodes\":[\n\t\t{\"id\":\"a\",\"type\":\"text\",\"text\":\"A\",\"x\":0,\"y\":0,\"width\":10,\"height\":10,\"styleAttributes\":{}},\n\t\t{\"id\":\"b\",\"type\":\"text\",\"text\":\"B\",  \"x\":20,\"y\":0,\"width\":10,\"height\":10},\n\t\t{\"id\":\"c\",\"type\":\"text\",\"text\":\"C\",\"x\":40,\"y\":0,\"width\":10.5,\"height\":10}\n\t],\n\t\"edges\":[\n\t\t{\"id\":\"e\",\"fromNode\":\"a\",\"toNode\":\"b\"}\n\t],\n\t\"metadata\": {\"version\": 1}\n}\n";
+
+    #[test]
+    fn unchanged_and_field_edits() {
+        let mut document: CanvasDocument = SOURCE.parse().unwrap();
+        assert_eq!(document.to_string(), SOURCE);
+
+        {
+            let mut node = document
+                .canvas_mut()
+                .get_node("a".parse().unwrap())
+                .unwrap();
+            node.set_color(Some(Color::Preset(PresetColor::Red)));
+            node.set_x(-5);
+        }
+        document
+            .canvas_mut()
+            .get_edge("e".parse().unwrap())
+            .unwrap()
+            .set_label("to".to_string());
+        assert_eq!(
+            document.to_string(),
+            SOURCE
+                .replace(
+                    "\"x\":0,\"y\":0,\"width\":10,\"height\":10,\"styleAttributes\":{}}",
+                    "\"x\":-5,\"y\":0,\"width\":10,\"height\":10,\"styleAttributes\":{},\"color\":\"1\"}"
+                )
+                .replace("\"toNode\":\"b\"}", "\"toNode\":\"b\",\"label\":\"to\"}")
+        );
+    }
+
+    #[test]
+    fn added_and_removed_items() {
+        let mut document: CanvasDocument = SOURCE.parse().unwrap();
+        let canvas = document.canvas_mut();
+        canvas.remove_node(&"b".parse().unwrap()).unwrap();
+        canvas
+            .add_node(Node::Text(TextNode::new(
+                "d".parse().unwrap(),
+                0,
+                20,
+                10,
+                10,
+                None,
+                "D".to_string(),
+            )))
+            .unwrap();
+        assert_eq!(
+            document.to_string(),
+            "{\n\t\"nodes\":[\n\t\t{\"id\":\"a\",\"type\":\"text\",\"text\":\"A\",\"x\":0,\"y\":0,\"width\":10,\"height\":10,\"styleAttributes\":{}},\n\t\t{\"id\":\"c\",\"type\":\"text\",\"text\":\"C\",\"x\":40,\"y\":0,\"width\":10.5,\"height\":10},\n\t\t{\"type\":\"text\",\"id\":\"d\",\"x\":0,\"y\":20,\"width\":10,\"height\":10,\"text\":\"D\"}\n\t],\n\t\"edges\":[],\n\t\"metadata\": {\"version\": 1}\n}\n"
+        );
+
+        let mut document: CanvasDocument = "{ \"nodes\": [] }".parse().unwrap();
+        let canvas = document.canvas_mut();
+        for id in ["a", "b"] {
+            canvas
+                .add_node(Node::Text(TextNode::new(
+                    id.parse().unwrap(),
+                    0,
+                    0,
+                    10,
+                    10,
+                    None,
+                    id.to_string(),
+                )))
+                .unwrap();
+        }
+        let edge = Edge::new(
+            "e".parse().unwrap(),
+            "a".parse().unwrap(),
+            None,
+            None,
+            "b".parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        );
+        canvas.add_edge(edge).unwrap();
+        let text = document.to_string();
+        assert!(text.starts_with("{ \"nodes\": [{\"type\":\"text\",\"id\":\"a\""));
+        assert!(
+            text.ends_with("], \"edges\": [{\"id\":\"e\",\"fromNode\":\"a\",\"toNode\":\"b\"}] }")
+        );
+    }
+
+    #[test]
+    fn reorder_only() {
+        let mut document: CanvasDocument = SOURCE.parse().unwrap();
+        document
+            .canvas_mut()
+            .move_node(&"c".parse().unwrap(), 0)
+            .unwrap();
+        assert!(document.is_modified());
+
+        assert_eq!(
+            document.to_string(),
+            "{\n\t\"nodes\":[\n\t\t{\"id\":\"c\",\"type\":\"text\",\"text\":\"C\",\"x\":40,\"y\":0,\"width\":10.5,\"height\":10},\n\t\t{\"id\":\"a\",\"type\":\"text\",\"text\":\"A\",\"x\":0,\"y\":0,\"width\":10,\"height\":10,\"styleAttributes\":{}},\n\t\t{\"id\":\"b\",\"type\":\"text\",\"text\":\"B\",  \"x\":20,\"y\":0,\"width\":10,\"height\":10}\n\t],\n\t\"edges\":[\n\t\t{\"id\":\"e\",\"fromNode\":\"a\",\"toNode\":\"b\"}\n\t],\n\t\"metadata\": {\"version\": 1}\n}\n"
+        );
+    }
+}
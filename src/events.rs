@@ -0,0 +1,447 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::edge::Edge;
+use crate::node::Node;
+use crate::{EdgeId, NodeId};
+
+/// Change made to a canvas, sent to the observers registered with
+/// [`JsonCanvas::subscribe`](crate::JsonCanvas::subscribe)
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum CanvasEvent {
+    NodeAdded(Node),
+    NodeRemoved(Node),
+    NodeUpdated {
+        old: Node,
+        new: Node,
+    },
+    EdgeAdded(Edge),
+    EdgeRemoved(Edge),
+    EdgeUpdated {
+        old: Edge,
+        new: Edge,
+    },
+    /// Sent after [`CanvasEvent::EdgeUpdated`] when the edge now links other nodes
+    EdgeReconnected {
+        id: EdgeId,
+        old: (NodeId, NodeId),
+        new: (NodeId, NodeId),
+    },
+    NodesReordered,
+    EdgesReordered,
+}
+
+/// Handle used to cancel a subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Observer = Arc<dyn Fn(&CanvasEvent) + Send + Sync>;
+
+/// Observers of a canvas.
+///
+/// They are tied to one canvas: a clone of the canvas starts without observers.
+#[derive(Default)]
+pub(crate) struct Observers {
+    next: u64,
+    list: Vec<(SubscriptionId, Observer)>,
+    /// Whether changes are counted even without observers, see [`Observers::changes`]
+    tracked: bool,
+    held: Mutex<Held>,
+}
+
+/// Events of the edits in progress, see [`Observers::hold`]
+#[derive(Default)]
+struct Held {
+    depth: usize,
+    events: Vec<CanvasEvent>,
+    changes: u64,
+}
+
+/// Where a hold started, to drop what was done since
+#[derive(Clone, Copy)]
+pub(crate) struct Mark {
+    events: usize,
+    changes: u64,
+}
+
+impl Observers {
+    pub(crate) fn subscribe(&mut self, observer: Observer) -> SubscriptionId {
+        let id = SubscriptionId(self.next);
+        self.next += 1;
+        self.list.push((id, observer));
+        id
+    }
+
+    pub(crate) fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.list.len();
+        self.list.retain(|(other, _)| *other != id);
+        self.list.len() != len
+    }
+
+    /// Whether the guards must keep the old value of what they change
+    pub(crate) fn is_watching(&self) -> bool {
+        self.tracked || !self.list.is_empty()
+    }
+
+    pub(crate) fn set_tracked(&mut self, tracked: bool) {
+        self.tracked = tracked;
+    }
+
+    /// Number of changes made so far, including the ones made through mutable
+    /// references, which are counted without checking them. Only meaningful while
+    /// tracked, as the guards skip unchanged items then.
+    pub(crate) fn changes(&self) -> u64 {
+        self.held().changes
+    }
+
+    /// Counts a change that may have been made
+    pub(crate) fn touch(&self) {
+        self.held().changes += 1;
+    }
+
+    /// Sends an event, only built if someone is listening
+    pub(crate) fn emit(&self, event: impl FnOnce() -> CanvasEvent) {
+        let mut held = self.held();
+        held.changes += 1;
+        if self.list.is_empty() {
+            return;
+        }
+        let event = event();
+        if held.depth > 0 {
+            held.events.push(event);
+            return;
+        }
+        drop(held);
+        self.send(&event);
+    }
+
+    fn send(&self, event: &CanvasEvent) {
+        for (_, observer) in &self.list {
+            observer(event);
+        }
+    }
+
+    fn held(&self) -> MutexGuard<'_, Held> {
+        self.held.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Holds back the events until the matching [`Observers::release`], returning
+    /// the mark to pass to it. Holds can be nested.
+    pub(crate) fn hold(&self) -> Mark {
+        let mut held = self.held();
+        held.depth += 1;
+        Mark {
+            events: held.events.len(),
+            changes: held.changes,
+        }
+    }
+
+    /// Ends a hold, dropping its events and changes unless `keep` is set. The
+    /// events are sent once the outermost hold ends.
+    pub(crate) fn release(&self, mark: Mark, keep: bool) {
+        let mut held = self.held();
+        held.depth -= 1;
+        if !keep {
+            held.events.truncate(mark.events);
+            held.changes = mark.changes;
+        }
+        if held.depth == 0 {
+            let events = std::mem::take(&mut held.events);
+            drop(held);
+            events.iter().for_each(|event| self.send(event));
+        }
+    }
+
+    pub(crate) fn emit_edge_update(&self, old: &Edge, new: &Edge) {
+        self.emit(|| CanvasEvent::EdgeUpdated {
+            old: old.clone(),
+            new: new.clone(),
+        });
+        if (old.from_node(), old.to_node()) != (new.from_node(), new.to_node()) {
+            self.emit(|| CanvasEvent::EdgeReconnected {
+                id: new.id().clone(),
+                old: (old.from_node().clone(), old.to_node().clone()),
+                new: (new.from_node().clone(), new.to_node().clone()),
+            });
+        }
+    }
+}
+
+/// Gives no observers: subscribers are not carried over to a clone of the canvas
+impl Clone for Observers {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Always equal, so that comparing canvases ignores who is observing them
+impl PartialEq for Observers {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Debug for Observers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} observer(s)", self.list.len())
+    }
+}
+
+/// Mutable access to a node, notifying the observers of the canvas when dropped
+pub struct NodeMut<'a> {
+    node: &'a mut Node,
+    observers: &'a Observers,
+    old: Option<Node>,
+}
+
+impl<'a> NodeMut<'a> {
+    pub(crate) fn new(node: &'a mut Node, observers: &'a Observers) -> Self {
+        let old = observers.is_watching().then(|| node.clone());
+        Self {
+            node,
+            observers,
+            old,
+        }
+    }
+}
+
+impl Deref for NodeMut<'_> {
+    type Target = Node;
+
+    fn deref(&self) -> &Node {
+        self.node
+    }
+}
+
+impl DerefMut for NodeMut<'_> {
+    fn deref_mut(&mut self) -> &mut Node {
+        self.node
+    }
+}
+
+impl Drop for NodeMut<'_> {
+    fn drop(&mut self) {
+        if let Some(old) = self.old.take() {
+            if old != *self.node {
+                self.observers.emit(|| CanvasEvent::NodeUpdated {
+                    old,
+                    new: self.node.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Mutable access to an edge, notifying the observers of the canvas when dropped
+pub struct EdgeMut<'a> {
+    edge: &'a mut Edge,
+    observers: &'a Observers,
+    old: Option<Edge>,
+}
+
+impl<'a> EdgeMut<'a> {
+    pub(crate) fn new(edge: &'a mut Edge, observers: &'a Observers) -> Self {
+        let old = observers.is_watching().then(|| edge.clone());
+        Self {
+            edge,
+            observers,
+            old,
+        }
+    }
+}
+
+impl Deref for EdgeMut<'_> {
+    type Target = Edge;
+
+    fn deref(&self) -> &Edge {
+        self.edge
+    }
+}
+
+impl DerefMut for EdgeMut<'_> {
+    fn deref_mut(&mut self) -> &mut Edge {
+        self.edge
+    }
+}
+
+impl Drop for EdgeMut<'_> {
+    fn drop(&mut self) {
+        if let Some(old) = self.old.take() {
+            if old != *self.edge {
+                self.observers.emit_edge_update(&old, self.edge);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::color::PresetColor;
+    use crate::node::{GenericNodeInfo, TextNode};
+    use crate::JsonCanvas;
+
+    fn text(id: &str) -> Node {
+        TextNode::new(id.parse().unwrap(), 0, 0, 100, 100, None, id.to_string()).into()
+    }
+
+    fn edge(from: &str, to: &str) -> Edge {
+        Edge::new(
+            "e".parse().unwrap(),
+            from.parse().unwrap(),
+            None,
+            None,
+            to.parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn record(canvas: &mut JsonCanvas) -> (SubscriptionId, Arc<Mutex<Vec<CanvasEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let id = canvas.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+        (id, events)
+    }
+
+    #[test]
+    fn events_from_edits() {
+        let mut canvas = JsonCanvas::default();
+        let (subscription, events) = record(&mut canvas);
+
+        canvas.add_node(text("a")).unwrap();
+        canvas.add_node(text("b")).unwrap();
+        canvas.add_edge(edge("a", "b")).unwrap();
+        canvas.node_mut(&"a".parse().unwrap()).unwrap().set_x(10);
+        canvas
+            .edge_mut(&"e".parse().unwrap())
+            .unwrap()
+            .set_to("a".parse().unwrap(), None, None);
+        canvas.get_node("a".parse().unwrap()).unwrap().set_y(10);
+        canvas.remove_node(&"b".parse().unwrap()).unwrap();
+
+        let events = std::mem::take(&mut *events.lock().unwrap());
+        let mut moved = text("a");
+        moved.set_x(10);
+        assert_eq!(
+            events[3],
+            CanvasEvent::NodeUpdated {
+                old: text("a"),
+                new: moved,
+            }
+        );
+        assert!(matches!(
+            &events[5],
+            CanvasEvent::EdgeReconnected { old, new, .. }
+                if old.1.as_str() == "b" && new.1.as_str() == "a"
+        ));
+        assert!(matches!(events[6], CanvasEvent::NodeUpdated { .. }));
+        assert!(matches!(events[7], CanvasEvent::NodeRemoved(_)));
+        assert_eq!(events.len(), 8);
+
+        assert!(canvas.unsubscribe(subscription));
+        assert!(!canvas.unsubscribe(subscription));
+    }
+
+    #[test]
+    fn events_from_accessors() {
+        let mut canvas = JsonCanvas::default();
+        canvas.add_node(text("a")).unwrap();
+        canvas.add_node(text("b")).unwrap();
+        canvas.add_edge(edge("a", "b")).unwrap();
+        let (_, events) = record(&mut canvas);
+
+        canvas
+            .get_node("a".parse().unwrap())
+            .unwrap()
+            .set_color(Some(PresetColor::Red.into()));
+        canvas
+            .get_edge("e".parse().unwrap())
+            .unwrap()
+            .set_label("to".to_string());
+        // Unchanged
+        canvas.get_node("b".parse().unwrap()).unwrap().set_y(0);
+
+        let events = events.lock().unwrap();
+        assert!(matches!(
+            &events[..],
+            [
+                CanvasEvent::NodeUpdated { old, new },
+                CanvasEvent::EdgeUpdated { new: edge, .. },
+            ] if old.color().is_none()
+                && new.color().is_some()
+                && edge.label().map(String::as_str) == Some("to")
+        ));
+    }
+
+    #[test]
+    fn events_from_reorders_and_patches() {
+        let mut canvas = JsonCanvas::default();
+        canvas.add_node(text("a")).unwrap();
+        canvas.add_node(text("b")).unwrap();
+        let before = canvas.clone();
+        let (_, events) = record(&mut canvas);
+
+        canvas.move_node(&"b".parse().unwrap(), 0).unwrap();
+        canvas
+            .node_mut(&"b".parse().unwrap())
+            .unwrap()
+            .set_color(Some(PresetColor::Red.into()));
+        assert!(matches!(
+            events.lock().unwrap()[..],
+            [CanvasEvent::NodesReordered, CanvasEvent::NodeUpdated { .. }]
+        ));
+        events.lock().unwrap().clear();
+
+        let patch = crate::patch::CanvasPatch::between(&canvas, &before);
+        canvas.apply(&patch).unwrap();
+        assert!(matches!(
+            events.lock().unwrap()[..],
+            [CanvasEvent::NodeUpdated { .. }, CanvasEvent::NodesReordered]
+        ));
+    }
+
+    #[test]
+    fn failed_edits_send_nothing() {
+        let mut canvas = JsonCanvas::default();
+        canvas.add_node(text("a")).unwrap();
+        let (_, events) = record(&mut canvas);
+
+        let patch = crate::patch::CanvasPatch::new(vec![
+            crate::patch::PatchOp::AddNode {
+                node: text("b"),
+                index: None,
+            },
+            crate::patch::PatchOp::AddNode {
+                node: text("a"),
+                index: None,
+            },
+        ]);
+        assert!(canvas.apply(&patch).is_err());
+        let result = canvas.transaction(|tx| {
+            tx.add_node(text("c"))?;
+            tx.add_edge(edge("c", "d"))
+        });
+        assert!(result.is_err());
+        assert!(events.lock().unwrap().is_empty());
+
+        // Nested edits are only sent once the outermost one succeeds
+        canvas
+            .transaction(|tx| {
+                tx.add_node(text("c"))?;
+                assert!(events.lock().unwrap().is_empty());
+                tx.add_node(text("d"))
+            })
+            .unwrap();
+        assert!(matches!(
+            events.lock().unwrap()[..],
+            [CanvasEvent::NodeAdded(_), CanvasEvent::NodeAdded(_)]
+        ));
+    }
+}
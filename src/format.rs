@@ -0,0 +1,349 @@
+//! Deterministic serialization, so that saving an unchanged canvas produces the same
+//! bytes and diffs in version control stay readable.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::node::GenericNodeInfo;
+use crate::JsonCanvas;
+
+/// How the document is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Everything on a single line, like [`JsonCanvas::to_string`]
+    #[default]
+    Compact,
+    /// Every field on its own line
+    Pretty,
+    /// Every node and edge on its own line, written compactly, like Obsidian does
+    OnePerLine,
+}
+
+/// Indentation of the [`Layout::Pretty`] and [`Layout::OnePerLine`] layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tab,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+/// Order of the nodes and edges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// Order of the canvas, which is the z-order of the nodes
+    #[default]
+    ZOrder,
+    /// Sorted by ID. This changes which node is drawn on top of the others when the
+    /// document is read back.
+    Id,
+}
+
+/// Order of the fields of each node and edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    /// Order of the fields in the types of this crate, with `type` first
+    #[default]
+    Declaration,
+    /// Order of the specification: `id`, `type`, position, size and color, then the
+    /// fields of each node type
+    Spec,
+    /// Order used by Obsidian: `id`, `type`, the fields of each node type, then
+    /// position, size and color
+    Obsidian,
+    Alphabetical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FormatOptions {
+    pub layout: Layout,
+    pub indent: Indent,
+    pub order: Order,
+    pub key_order: KeyOrder,
+    /// Whether empty `nodes` and `edges` arrays are written, instead of left out
+    pub empty_lists: bool,
+}
+
+impl FormatOptions {
+    /// Same output as Obsidian: one node or edge per line, indented with tabs
+    pub fn obsidian() -> Self {
+        FormatOptions {
+            layout: Layout::OnePerLine,
+            indent: Indent::Tab,
+            order: Order::ZOrder,
+            key_order: KeyOrder::Obsidian,
+            empty_lists: true,
+        }
+    }
+}
+
+const TYPE_FIELDS: &[&str] = &[
+    "text",
+    "file",
+    "subpath",
+    "url",
+    "label",
+    "background",
+    "backgroundStyle",
+];
+const GENERIC_FIELDS: &[&str] = &["x", "y", "width", "height", "color"];
+const EDGE_FIELDS: &[&str] = &[
+    "id", "fromNode", "fromSide", "fromEnd", "toNode", "toSide", "toEnd", "color", "label",
+];
+
+impl KeyOrder {
+    pub(crate) fn node_fields(self) -> Vec<&'static str> {
+        let (first, second) = match self {
+            KeyOrder::Declaration => (&["type", "id"], [GENERIC_FIELDS, TYPE_FIELDS]),
+            KeyOrder::Spec => (&["id", "type"], [GENERIC_FIELDS, TYPE_FIELDS]),
+            KeyOrder::Obsidian => (&["id", "type"], [TYPE_FIELDS, GENERIC_FIELDS]),
+            KeyOrder::Alphabetical => return Vec::new(),
+        };
+        first
+            .iter()
+            .chain(second.concat().iter())
+            .copied()
+            .collect()
+    }
+
+    pub(crate) fn edge_fields(self) -> Vec<&'static str> {
+        match self {
+            KeyOrder::Alphabetical => Vec::new(),
+            _ => EDGE_FIELDS.to_vec(),
+        }
+    }
+}
+
+/// Fields of `object` in the order of `fields`, followed by any other field sorted by
+/// name
+fn ordered<'a>(object: &'a Map<String, Value>, fields: &[&str]) -> Vec<(&'a String, &'a Value)> {
+    let mut entries: Vec<_> = object.iter().collect();
+    entries.sort_by_key(|(key, _)| {
+        let position = fields.iter().position(|field| field == key);
+        (position.unwrap_or(fields.len()), *key)
+    });
+    entries
+}
+
+pub(crate) fn to_value(value: &impl Serialize) -> Value {
+    serde_json::to_value(value).expect("a canvas is valid JSON")
+}
+
+pub(crate) struct Writer<'a> {
+    pub(crate) options: &'a FormatOptions,
+    pub(crate) out: String,
+}
+
+impl Writer<'_> {
+    pub(crate) fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        for _ in 0..depth {
+            match self.options.indent {
+                Indent::Spaces(n) => self.out.push_str(&" ".repeat(n)),
+                Indent::Tab => self.out.push('\t'),
+            }
+        }
+    }
+
+    pub(crate) fn key(&mut self, key: &str) {
+        self.out.push_str(&Value::from(key).to_string());
+        self.out.push(':');
+        if self.options.layout == Layout::Pretty {
+            self.out.push(' ');
+        }
+    }
+
+    /// Writes an object, breaking lines inside it if `multiline`
+    fn object(&mut self, entries: &[(&String, &Value)], depth: usize, multiline: bool) {
+        if entries.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push('{');
+        for (index, (key, value)) in entries.iter().enumerate() {
+            if index > 0 {
+                self.out.push(',');
+            }
+            if multiline {
+                self.newline(depth + 1);
+            }
+            self.key(key);
+            self.value(value, depth + 1, multiline);
+        }
+        if multiline {
+            self.newline(depth);
+        }
+        self.out.push('}');
+    }
+
+    fn value(&mut self, value: &Value, depth: usize, multiline: bool) {
+        match value {
+            Value::Object(object) => {
+                let entries = ordered(object, &[]);
+                self.object(&entries, depth, multiline);
+            }
+            Value::Array(items) if multiline && !items.is_empty() => {
+                self.out.push('[');
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push(',');
+                    }
+                    self.newline(depth + 1);
+                    self.value(item, depth + 1, multiline);
+                }
+                self.newline(depth);
+                self.out.push(']');
+            }
+            Value::Array(items) => {
+                self.out.push('[');
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push(',');
+                    }
+                    self.value(item, depth, false);
+                }
+                self.out.push(']');
+            }
+            _ => self.out.push_str(&value.to_string()),
+        }
+    }
+
+    /// Writes a node or edge, with its fields in the order of `fields`
+    pub(crate) fn item(&mut self, item: &Value, fields: &[&str], depth: usize) {
+        match item {
+            Value::Object(object) => {
+                let entries = ordered(object, fields);
+                self.object(&entries, depth, self.options.layout == Layout::Pretty);
+            }
+            other => self.value(other, depth, false),
+        }
+    }
+
+    fn items(&mut self, items: Vec<Value>, fields: &[&str], depth: usize) {
+        let layout = self.options.layout;
+        if items.is_empty() {
+            self.out.push_str("[]");
+            return;
+        }
+        self.out.push('[');
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.out.push(',');
+            }
+            if layout != Layout::Compact {
+                self.newline(depth + 1);
+            }
+            self.item(item, fields, depth + 1);
+        }
+        if layout != Layout::Compact {
+            self.newline(depth);
+        }
+        self.out.push(']');
+    }
+}
+
+impl JsonCanvas {
+    /// Serializes the canvas according to `options`
+    ///
+    /// ```
+    /// use jsoncanvas::format::FormatOptions;
+    /// use jsoncanvas::JsonCanvas;
+    ///
+    /// let source = "{\n\t\"nodes\":[\n\t\t{\"id\":\"a\",\"type\":\"text\",\"text\":\"A\",\"x\":0,\"y\":0,\"width\":10,\"height\":10}\n\t],\n\t\"edges\":[]\n}";
+    /// let canvas: JsonCanvas = source.parse().unwrap();
+    /// assert_eq!(canvas.to_string_with(&FormatOptions::obsidian()), source);
+    /// ```
+    pub fn to_string_with(&self, options: &FormatOptions) -> String {
+        let mut nodes: Vec<_> = self.nodes().collect();
+        let mut edges: Vec<_> = self.edges().collect();
+        if options.order == Order::Id {
+            nodes.sort_by(|a, b| a.id().cmp(b.id()));
+            edges.sort_by(|a, b| a.id().cmp(b.id()));
+        }
+        let nodes: Vec<Value> = nodes.into_iter().map(to_value).collect();
+        let edges: Vec<Value> = edges.into_iter().map(to_value).collect();
+
+        let mut writer = Writer {
+            options,
+            out: String::new(),
+        };
+        let multiline = options.layout != Layout::Compact;
+        writer.out.push('{');
+        let lists = [
+            ("nodes", nodes, options.key_order.node_fields()),
+            ("edges", edges, options.key_order.edge_fields()),
+        ];
+        let mut first = true;
+        for (key, items, fields) in lists {
+            if items.is_empty() && !options.empty_lists {
+                continue;
+            }
+            if !first {
+                writer.out.push(',');
+            }
+            first = false;
+            if multiline {
+                writer.newline(1);
+            }
+            writer.key(key);
+            writer.items(items, &fields, 1);
+        }
+        if multiline && !first {
+            writer.newline(0);
+        }
+        writer.out.push('}');
+        writer.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"{"nodes":[{"type":"text","id":"b","x":0,"y":0,"width":10,"height":10,"color":"1","text":"B"},{"type":"group","id":"a","x":-5,"y":-5,"width":30,"height":30,"label":"G"}],"edges":[{"id":"e","fromNode":"b","toNode":"a","label":"to"}]}"#;
+
+    #[test]
+    fn default_matches_display() {
+        let canvas: JsonCanvas = SOURCE.parse().unwrap();
+        assert_eq!(canvas.to_string_with(&FormatOptions::default()), SOURCE);
+        assert_eq!(
+            JsonCanvas::default().to_string_with(&FormatOptions::default()),
+            JsonCanvas::default().to_string()
+        );
+    }
+
+    #[test]
+    fn pretty_sorted() {
+        let canvas: JsonCanvas = SOURCE.parse().unwrap();
+        let options = FormatOptions {
+            layout: Layout::Pretty,
+            order: Order::Id,
+            key_order: KeyOrder::Spec,
+            ..Default::default()
+        };
+        let pretty = canvas.to_string_with(&options);
+        assert!(pretty.starts_with(
+            "{\n  \"nodes\": [\n    {\n      \"id\": \"a\",\n      \"type\": \"group\",\n      \"x\": -5,"
+        ));
+        assert!(pretty.ends_with("      \"label\": \"to\"\n    }\n  ]\n}"));
+        assert_eq!(
+            pretty
+                .parse::<JsonCanvas>()
+                .unwrap()
+                .to_string_with(&options),
+            pretty
+        );
+
+        let options = FormatOptions {
+            key_order: KeyOrder::Alphabetical,
+            ..Default::default()
+        };
+        assert!(canvas.to_string_with(&options).starts_with(
+            r#"{"nodes":[{"color":"1","height":10,"id":"b","text":"B","type":"text","#
+        ));
+    }
+}
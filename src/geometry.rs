@@ -0,0 +1,114 @@
+use crate::node::{GenericNodeInfo, Node};
+use crate::{JsonCanvas, NodeId, PixelCoordinate, PixelDimension};
+
+/// Axis-aligned rectangle covered by a node on the canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: PixelCoordinate,
+    pub y: PixelCoordinate,
+    pub width: PixelDimension,
+    pub height: PixelDimension,
+}
+
+impl Rect {
+    pub fn new(
+        x: PixelCoordinate,
+        y: PixelCoordinate,
+        width: PixelDimension,
+        height: PixelDimension,
+    ) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn of<N: GenericNodeInfo + ?Sized>(node: &N) -> Self {
+        Self::new(
+            node.get_x(),
+            node.get_y(),
+            node.get_width(),
+            node.get_height(),
+        )
+    }
+
+    pub fn right(&self) -> PixelCoordinate {
+        self.x + self.width as PixelCoordinate
+    }
+
+    pub fn bottom(&self) -> PixelCoordinate {
+        self.y + self.height as PixelCoordinate
+    }
+
+    /// Returns true if both rectangles share an area (touching borders do not count)
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        Some(Rect::new(
+            x,
+            y,
+            (right - x) as PixelDimension,
+            (bottom - y) as PixelDimension,
+        ))
+    }
+
+    /// Returns true if `other` lies entirely inside this rectangle
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        Rect::new(
+            x,
+            y,
+            (right - x) as PixelDimension,
+            (bottom - y) as PixelDimension,
+        )
+    }
+}
+
+impl JsonCanvas {
+    /// Smallest rectangle holding every node, or `None` for a canvas without nodes
+    pub fn bounds(&self) -> Option<Rect> {
+        self.get_nodes()
+            .values()
+            .map(Rect::of)
+            .reduce(|bounds, rect| bounds.union(&rect))
+    }
+
+    /// IDs of the nodes lying inside the given group, in canvas order.
+    ///
+    /// Returns an empty list if the node does not exist or is not a group.
+    pub fn group_content(&self, id: &NodeId) -> Vec<NodeId> {
+        let Some(Node::Group(group)) = self.get_nodes().get(id) else {
+            return Vec::new();
+        };
+        let area = Rect::of(group);
+        self.get_nodes()
+            .values()
+            .filter(|node| node.id() != id && area.contains(&Rect::of(*node)))
+            .map(|node| node.id().clone())
+            .collect()
+    }
+}
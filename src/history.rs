@@ -0,0 +1,394 @@
+use std::collections::VecDeque;
+
+use crate::color::Color;
+use crate::edge::{Edge, Terminus};
+use crate::node::Node;
+use crate::patch::{CanvasPatch, PatchOp};
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId, PixelCoordinate};
+
+/// A reversible edit of a canvas, executed through a [`History`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    AddNode(Node),
+    /// Removes a node along with its edges
+    RemoveNode(NodeId),
+    MoveNode {
+        id: NodeId,
+        x: PixelCoordinate,
+        y: PixelCoordinate,
+    },
+    RestyleNode {
+        id: NodeId,
+        color: Option<Color>,
+    },
+    AddEdge(Edge),
+    RemoveEdge(EdgeId),
+    RestyleEdge {
+        id: EdgeId,
+        color: Option<Color>,
+    },
+    Reconnect {
+        id: EdgeId,
+        from: Terminus,
+        to: Terminus,
+    },
+    /// Commands undone and redone as a single step
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    /// Applies the command, returning the patch it applied
+    pub(crate) fn run(&self, canvas: &mut JsonCanvas) -> Result<CanvasPatch, JsonCanvasError> {
+        let patch = match self {
+            Command::Batch(commands) => {
+                return canvas.atomically(|canvas| {
+                    let mut patch = CanvasPatch::default();
+                    for command in commands {
+                        match command.run(canvas) {
+                            Ok(done) => done.ops().iter().for_each(|op| patch.push(op.clone())),
+                            Err(err) => {
+                                canvas
+                                    .apply(&patch.invert())
+                                    .expect("inverse of an applied patch must apply");
+                                return Err(err);
+                            }
+                        }
+                    }
+                    Ok(patch)
+                });
+            }
+            Command::AddNode(node) => CanvasPatch::new(vec![PatchOp::AddNode {
+                node: node.clone(),
+                index: None,
+            }]),
+            Command::RemoveNode(id) => {
+                let index = canvas
+                    .ordered_nodes()
+                    .get_index_of(id)
+                    .ok_or_else(|| JsonCanvasError::NodeNotExists(id.clone()))?;
+                let edges: Vec<&Edge> = canvas.edges().collect();
+                let mut ops: Vec<PatchOp> = edges
+                    .into_iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, edge)| edge.from_node() == id || edge.to_node() == id)
+                    .map(|(index, edge)| PatchOp::RemoveEdge {
+                        edge: edge.clone(),
+                        index: Some(index),
+                    })
+                    .collect();
+                ops.push(PatchOp::RemoveNode {
+                    node: canvas.get_nodes()[id].clone(),
+                    index: Some(index),
+                });
+                CanvasPatch::new(ops)
+            }
+            Command::MoveNode { id, x, y } => update_node(canvas, id, |node| {
+                node.set_x(*x);
+                node.set_y(*y);
+            })?,
+            Command::RestyleNode { id, color } => {
+                update_node(canvas, id, |node| node.set_color(*color))?
+            }
+            Command::AddEdge(edge) => CanvasPatch::new(vec![PatchOp::AddEdge {
+                edge: edge.clone(),
+                index: None,
+            }]),
+            Command::RemoveEdge(id) => {
+                let index = canvas
+                    .ordered_edges()
+                    .get_index_of(id)
+                    .ok_or_else(|| JsonCanvasError::EdgeNotExists(id.clone()))?;
+                CanvasPatch::new(vec![PatchOp::RemoveEdge {
+                    edge: canvas.get_edges()[id].clone(),
+                    index: Some(index),
+                }])
+            }
+            Command::RestyleEdge { id, color } => update_edge(canvas, id, |edge| match color {
+                Some(color) => {
+                    edge.set_color(*color);
+                }
+                None => {
+                    edge.remove_color();
+                }
+            })?,
+            Command::Reconnect { id, from, to } => update_edge(canvas, id, |edge| {
+                edge.set_from(from.0.clone(), from.1, from.2);
+                edge.set_to(to.0.clone(), to.1, to.2);
+            })?,
+        };
+        canvas.apply(&patch)?;
+        Ok(patch)
+    }
+}
+
+pub(crate) fn update_node(
+    canvas: &JsonCanvas,
+    id: &NodeId,
+    update: impl FnOnce(&mut Node),
+) -> Result<CanvasPatch, JsonCanvasError> {
+    let from = canvas
+        .get_nodes()
+        .get(id)
+        .ok_or_else(|| JsonCanvasError::NodeNotExists(id.clone()))?;
+    let mut to = from.clone();
+    update(&mut to);
+    Ok(CanvasPatch::new(vec![PatchOp::UpdateNode {
+        from: from.clone(),
+        to,
+    }]))
+}
+
+pub(crate) fn update_edge(
+    canvas: &JsonCanvas,
+    id: &EdgeId,
+    update: impl FnOnce(&mut Edge),
+) -> Result<CanvasPatch, JsonCanvasError> {
+    let from = canvas
+        .get_edges()
+        .get(id)
+        .ok_or_else(|| JsonCanvasError::EdgeNotExists(id.clone()))?;
+    let mut to = from.clone();
+    update(&mut to);
+    Ok(CanvasPatch::new(vec![PatchOp::UpdateEdge {
+        from: from.clone(),
+        to,
+    }]))
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    patch: CanvasPatch,
+    /// Node moved by the entry, if it was a single move that later moves may extend
+    moved: Option<NodeId>,
+}
+
+/// Undo and redo stacks of the commands executed on a canvas.
+///
+/// Consecutive moves of the same node, as done while dragging it, are merged into
+/// a single step until [`History::seal`] is called.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    limit: Option<usize>,
+    sealed: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a history keeping at most `limit` steps to undo
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Default::default()
+        }
+    }
+
+    /// Executes the command on the canvas and records it.
+    ///
+    /// On failure, the canvas is left untouched and nothing is recorded.
+    pub fn execute(
+        &mut self,
+        canvas: &mut JsonCanvas,
+        command: Command,
+    ) -> Result<(), JsonCanvasError> {
+        let patch = command.run(canvas)?;
+        self.redo.clear();
+
+        let moved = match command {
+            Command::MoveNode { id, .. } => Some(id),
+            _ => None,
+        };
+        if let (Some(id), false) = (&moved, self.sealed) {
+            if let Some(last) = self.undo.back_mut() {
+                if last.moved.as_ref() == Some(id) {
+                    last.patch = merge_updates(&last.patch, &patch);
+                    return Ok(());
+                }
+            }
+        }
+
+        self.sealed = false;
+        self.undo.push_back(Entry { patch, moved });
+        if let Some(limit) = self.limit {
+            while self.undo.len() > limit {
+                self.undo.pop_front();
+            }
+        }
+        Ok(())
+    }
+
+    /// Prevents the next command from being merged with the last one, e.g. at the
+    /// end of a drag
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// Undoes the last step, returning false if there is nothing to undo
+    pub fn undo(&mut self, canvas: &mut JsonCanvas) -> Result<bool, JsonCanvasError> {
+        let Some(entry) = self.undo.pop_back() else {
+            return Ok(false);
+        };
+        if let Err(err) = canvas.apply(&entry.patch.invert()) {
+            self.undo.push_back(entry);
+            return Err(err);
+        }
+        self.redo.push(entry);
+        self.sealed = true;
+        Ok(true)
+    }
+
+    /// Redoes the last undone step, returning false if there is nothing to redo
+    pub fn redo(&mut self, canvas: &mut JsonCanvas) -> Result<bool, JsonCanvasError> {
+        let Some(entry) = self.redo.pop() else {
+            return Ok(false);
+        };
+        if let Err(err) = canvas.apply(&entry.patch) {
+            self.redo.push(entry);
+            return Err(err);
+        }
+        self.undo.push_back(entry);
+        self.sealed = true;
+        Ok(true)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// Combines two single-update patches of the same node into one
+fn merge_updates(first: &CanvasPatch, second: &CanvasPatch) -> CanvasPatch {
+    match (first.ops(), second.ops()) {
+        ([PatchOp::UpdateNode { from, .. }], [PatchOp::UpdateNode { to, .. }]) => {
+            CanvasPatch::new(vec![PatchOp::UpdateNode {
+                from: from.clone(),
+                to: to.clone(),
+            }])
+        }
+        _ => unreachable!("moves are single node updates"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::PresetColor;
+    use crate::node::{GenericNodeInfo, TextNode};
+
+    fn text(id: &str) -> Node {
+        TextNode::new(id.parse().unwrap(), 0, 0, 100, 100, None, id.to_string()).into()
+    }
+
+    fn edge() -> Edge {
+        Edge::new(
+            "ab".parse().unwrap(),
+            "a".parse().unwrap(),
+            None,
+            None,
+            "b".parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn position(canvas: &JsonCanvas, id: &str) -> (PixelCoordinate, PixelCoordinate) {
+        let node = &canvas.get_nodes()[&id.parse::<NodeId>().unwrap()];
+        (node.get_x(), node.get_y())
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut canvas = JsonCanvas::default();
+        let mut history = History::new();
+        history
+            .execute(
+                &mut canvas,
+                Command::Batch(vec![
+                    Command::AddNode(text("a")),
+                    Command::AddNode(text("b")),
+                    Command::AddEdge(edge()),
+                ]),
+            )
+            .unwrap();
+        let built = canvas.clone();
+        history
+            .execute(&mut canvas, Command::RemoveNode("a".parse().unwrap()))
+            .unwrap();
+        history
+            .execute(
+                &mut canvas,
+                Command::RestyleNode {
+                    id: "b".parse().unwrap(),
+                    color: Some(PresetColor::Red.into()),
+                },
+            )
+            .unwrap();
+
+        assert!(history.undo(&mut canvas).unwrap());
+        assert!(history.undo(&mut canvas).unwrap());
+        assert_eq!(canvas, built);
+        assert!(history.undo(&mut canvas).unwrap());
+        assert_eq!(canvas, JsonCanvas::default());
+        assert!(!history.undo(&mut canvas).unwrap());
+
+        assert!(history.redo(&mut canvas).unwrap());
+        assert_eq!(canvas, built);
+    }
+
+    #[test]
+    fn failed_batch_is_rolled_back() {
+        let mut canvas = JsonCanvas::default();
+        let mut history = History::new();
+        let result = history.execute(
+            &mut canvas,
+            Command::Batch(vec![Command::AddNode(text("a")), Command::AddEdge(edge())]),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(canvas, JsonCanvas::default());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn drags_are_merged() {
+        let mut canvas = JsonCanvas::default();
+        let mut history = History::with_limit(2);
+        history
+            .execute(&mut canvas, Command::AddNode(text("a")))
+            .unwrap();
+        for x in 1..=10 {
+            let id = "a".parse().unwrap();
+            history
+                .execute(&mut canvas, Command::MoveNode { id, x, y: x })
+                .unwrap();
+        }
+        history.seal();
+        let id = "a".parse().unwrap();
+        history
+            .execute(&mut canvas, Command::MoveNode { id, x: 50, y: 50 })
+            .unwrap();
+
+        history.undo(&mut canvas).unwrap();
+        assert_eq!(position(&canvas, "a"), (10, 10));
+        history.undo(&mut canvas).unwrap();
+        assert_eq!(position(&canvas, "a"), (0, 0));
+        // The node addition was dropped by the limit
+        assert!(!history.can_undo());
+    }
+}
@@ -1,14 +1,27 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::{fmt::Display, str::FromStr};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, thiserror::Error)]
 #[error("ID is empty")]
 pub struct EmptyId;
 
+fn random_hex() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:016x}", hasher.finish())
+}
+
 macro_rules! id_type {
     ($($name: ident),+) => { $(
-        #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+        #[derive(
+            Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+        )]
         #[repr(transparent)]
         #[serde(transparent)]
         pub struct $name(pub(self) String);
@@ -21,6 +34,16 @@ macro_rules! id_type {
             pub fn as_str(&self) -> &str {
                 &self.0
             }
+
+            /// Same as deserializing, which does not reject empty IDs
+            pub(crate) fn new_unchecked(value: String) -> Self {
+                Self(value)
+            }
+
+            /// Generates a random ID made of 16 hexadecimal digits, like Obsidian does
+            pub fn random() -> Self {
+                Self(random_hex())
+            }
         }
 
         impl Display for $name {
@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+
+use crate::events::CanvasEvent;
+use crate::geometry::Rect;
+use crate::node::{GenericNodeInfo, GroupNode, Node};
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId, PixelCoordinate, PixelDimension};
+
+/// Space left between a wrapping group and the imported nodes
+const GROUP_PADDING: PixelDimension = 20;
+
+/// How to choose the IDs of imported nodes and edges
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdPolicy {
+    /// Keep every ID, failing on collisions
+    Keep,
+    /// Prefix the IDs already used in the canvas, as many times as needed. With an
+    /// empty prefix, a number is appended instead.
+    Prefix(String),
+    /// Generate new IDs for the ones already used in the canvas
+    Regenerate,
+    /// Generate new IDs for every node and edge
+    RegenerateAll,
+}
+
+/// IDs given to the imported nodes and edges, indexed by their original ID
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IdMapping {
+    pub nodes: IndexMap<NodeId, NodeId>,
+    pub edges: IndexMap<EdgeId, EdgeId>,
+}
+
+impl JsonCanvas {
+    /// Adds every node and edge of `other`, moved by `offset`.
+    ///
+    /// IDs are chosen according to `id_policy`, and edges are rewritten to point to
+    /// the new node IDs. Nothing is imported if an ID collides under
+    /// [`IdPolicy::Keep`].
+    pub fn import(
+        &mut self,
+        other: JsonCanvas,
+        offset: (PixelCoordinate, PixelCoordinate),
+        id_policy: IdPolicy,
+    ) -> Result<IdMapping, JsonCanvasError> {
+        let mut mapping = IdMapping::default();
+
+        let mut used: HashSet<NodeId> = self.get_nodes().keys().cloned().collect();
+        for id in other.nodes().map(|node| node.id()) {
+            let new_id = new_id(id, &id_policy, &mut used)
+                .ok_or_else(|| JsonCanvasError::NodeExists(id.clone()))?;
+            mapping.nodes.insert(id.clone(), new_id);
+        }
+        let mut used: HashSet<EdgeId> = self.get_edges().keys().cloned().collect();
+        for edge in other.edges() {
+            let id = edge.id();
+            for node in [edge.from_node(), edge.to_node()] {
+                if !mapping.nodes.contains_key(node) {
+                    return Err(JsonCanvasError::NodeNotExists(node.clone()));
+                }
+            }
+            let new_id = new_id(id, &id_policy, &mut used)
+                .ok_or_else(|| JsonCanvasError::EdgeExists(id.clone()))?;
+            mapping.edges.insert(id.clone(), new_id);
+        }
+
+        let (nodes, edges) = other.into_parts();
+        for (id, mut node) in nodes.into_entries() {
+            node.set_id(mapping.nodes[&id].clone());
+            node.translate(offset.0, offset.1);
+            self.add_node(node)?;
+        }
+        for (id, mut edge) in edges.into_entries() {
+            edge.id = mapping.edges[&id].clone();
+            edge.from_node = mapping.nodes[&edge.from_node].clone();
+            edge.to_node = mapping.nodes[&edge.to_node].clone();
+            self.add_edge(edge)?;
+        }
+
+        Ok(mapping)
+    }
+
+    /// Same as [`JsonCanvas::import`], wrapping the imported nodes in a new group.
+    ///
+    /// The group is placed below the imported nodes, and its ID is returned along
+    /// with the mapping.
+    pub fn import_as_group(
+        &mut self,
+        other: JsonCanvas,
+        offset: (PixelCoordinate, PixelCoordinate),
+        id_policy: IdPolicy,
+        label: Option<String>,
+    ) -> Result<(NodeId, IdMapping), JsonCanvasError> {
+        let padding = GROUP_PADDING as PixelCoordinate;
+        let bounds = other.bounds().unwrap_or(Rect::new(0, 0, 0, 0));
+        let index = self.get_nodes().len();
+        let mapping = self.import(other, offset, id_policy)?;
+
+        let mut group_id = NodeId::random();
+        while self.get_nodes().contains_key(&group_id) {
+            group_id = NodeId::random();
+        }
+        let group = GroupNode::new(
+            group_id.clone(),
+            bounds.x + offset.0 - padding,
+            bounds.y + offset.1 - padding,
+            bounds.width + 2 * GROUP_PADDING,
+            bounds.height + 2 * GROUP_PADDING,
+            None,
+            label,
+            None,
+        );
+        let group: Node = group.into();
+        self.emit(|| CanvasEvent::NodeAdded(group.clone()));
+        self.unobserved_nodes_mut()
+            .insert_at(index, group_id.clone(), group);
+
+        Ok((group_id, mapping))
+    }
+}
+
+fn new_id<T>(id: &T, policy: &IdPolicy, used: &mut HashSet<T>) -> Option<T>
+where
+    T: Hash + Eq + Clone + Display + FromStr,
+{
+    let random = || loop {
+        let id = T::from_str(&NodeId::random().into_inner()).ok()?;
+        if !used.contains(&id) {
+            return Some(id);
+        }
+    };
+
+    let new_id = match policy {
+        IdPolicy::RegenerateAll => random()?,
+        _ if !used.contains(id) => id.clone(),
+        IdPolicy::Keep => return None,
+        IdPolicy::Regenerate => random()?,
+        IdPolicy::Prefix(prefix) if prefix.is_empty() => {
+            let mut number = 1;
+            loop {
+                let new_id = T::from_str(&format!("{}-{}", id, number)).ok()?;
+                if !used.contains(&new_id) {
+                    break new_id;
+                }
+                number += 1;
+            }
+        }
+        IdPolicy::Prefix(prefix) => {
+            let mut new_id = id.clone();
+            while used.contains(&new_id) {
+                new_id = T::from_str(&format!("{}{}", prefix, new_id)).ok()?;
+            }
+            new_id
+        }
+    };
+    used.insert(new_id.clone());
+    Some(new_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+    use crate::node::TextNode;
+
+    fn source() -> JsonCanvas {
+        let mut canvas = JsonCanvas::default();
+        for (id, x) in [("id1", 0), ("id2", 200)] {
+            canvas
+                .add_node(
+                    TextNode::new(id.parse().unwrap(), x, 0, 100, 50, None, id.to_string()).into(),
+                )
+                .unwrap();
+        }
+        canvas
+            .add_edge(Edge::new(
+                "edge1".parse().unwrap(),
+                "id1".parse().unwrap(),
+                None,
+                None,
+                "id2".parse().unwrap(),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+        canvas
+    }
+
+    #[test]
+    fn prefix_colliding_ids() {
+        let mut canvas = source();
+        let mapping = canvas
+            .import(source(), (0, 500), IdPolicy::Prefix("b-".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            mapping.nodes[&"id1".parse::<NodeId>().unwrap()].as_str(),
+            "b-id1"
+        );
+        let edge = &canvas.get_edges()[&"b-edge1".parse::<EdgeId>().unwrap()];
+        assert_eq!(edge.from_node().as_str(), "b-id1");
+        assert_eq!(edge.to_node().as_str(), "b-id2");
+        assert_eq!(
+            canvas.get_nodes()[&"b-id2".parse::<NodeId>().unwrap()].get_y(),
+            500
+        );
+    }
+
+    #[test]
+    fn number_with_empty_prefix() {
+        let mut canvas = source();
+        for _ in 0..2 {
+            canvas
+                .import(source(), (0, 0), IdPolicy::Prefix(String::new()))
+                .unwrap();
+        }
+
+        let mut ids: Vec<&str> = canvas.nodes().map(|node| node.id().as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["id1", "id1-1", "id1-2", "id2", "id2-1", "id2-2"]);
+        assert!(canvas
+            .get_edges()
+            .contains_key(&"edge1-2".parse::<EdgeId>().unwrap()));
+    }
+
+    #[test]
+    fn keeps_float_coordinates() {
+        let other: JsonCanvas = r#"{"nodes":[{"type":"text","id":"a","x":-120.5,"y":0.25,"width":10,"height":10,"text":"a"}]}"#
+            .parse()
+            .unwrap();
+        let mut canvas = JsonCanvas::default();
+        canvas.import(other, (10, 5), IdPolicy::Keep).unwrap();
+
+        let node = canvas.nodes().next().unwrap();
+        assert_eq!((node.get_exact_x(), node.get_exact_y()), (-110.5, 5.25));
+        assert!(canvas.to_string().contains(r#""x":-110.5,"y":5.25"#));
+    }
+
+    #[test]
+    fn keep_fails_without_changes() {
+        let mut canvas = source();
+        assert!(matches!(
+            canvas.import(source(), (0, 0), IdPolicy::Keep),
+            Err(JsonCanvasError::NodeExists(_))
+        ));
+        assert_eq!(canvas, source());
+    }
+
+    #[test]
+    fn wrap_in_group() {
+        let mut canvas = source();
+        let (group, mapping) = canvas
+            .import_as_group(
+                source(),
+                (1000, 0),
+                IdPolicy::Regenerate,
+                Some("Imported".to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(canvas.get_nodes().len(), 5);
+        assert_eq!(canvas.ordered_nodes().get_index_of(&group), Some(2));
+        let Node::Group(group) = &canvas.get_nodes()[&group] else {
+            panic!("expected a group");
+        };
+        assert_eq!(Rect::of(group), Rect::new(980, -20, 340, 90));
+        assert_eq!(group.label().map(String::as_str), Some("Imported"));
+        assert!(mapping
+            .nodes
+            .values()
+            .all(|id| !["id1", "id2"].contains(&id.as_str())));
+    }
+}
@@ -0,0 +1,192 @@
+//! Reading and writing canvas files.
+//!
+//! Files are saved atomically: the canvas is written to a temporary file next to the
+//! destination, which is then renamed over it, so a crash never leaves a truncated
+//! file behind.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::format::FormatOptions;
+use crate::parse;
+use crate::{JsonCanvas, JsonCanvasError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SaveOptions {
+    pub format: FormatOptions,
+    /// Number of previous versions to keep, as `name.canvas.bak`, then
+    /// `name.canvas.bak.1` and so on, the most recent first
+    pub backups: usize,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> JsonCanvasError + '_ {
+    |source| JsonCanvasError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => with_suffix(path, ".bak"),
+        _ => with_suffix(path, &format!(".bak.{}", index)),
+    }
+}
+
+/// Shifts the existing backups of `path` and copies it as the most recent one
+fn rotate_backups(path: &Path, backups: usize) -> Result<(), JsonCanvasError> {
+    if backups == 0 || !path.exists() {
+        return Ok(());
+    }
+    for index in (1..backups).rev() {
+        let from = backup_path(path, index - 1);
+        if from.exists() {
+            let to = backup_path(path, index);
+            fs::rename(&from, &to).map_err(io_error(&from))?;
+        }
+    }
+    let backup = backup_path(path, 0);
+    fs::copy(path, &backup).map_err(io_error(&backup))?;
+    Ok(())
+}
+
+/// Distinguishes the temporary files of the threads of this process
+static TEMPORARY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Writes `contents` to a temporary file, then renames it to `path`
+fn write_atomic(path: &Path, contents: &str) -> Result<(), JsonCanvasError> {
+    let count = TEMPORARY_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temporary = with_suffix(path, &format!(".{}.{}.tmp", std::process::id(), count));
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create_new(&temporary)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    };
+    if let Err(source) = write().and_then(|_| fs::rename(&temporary, path)) {
+        let _ = fs::remove_file(&temporary);
+        return Err(JsonCanvasError::Io {
+            path: path.to_path_buf(),
+            source,
+        });
+    }
+    sync_parent(path).map_err(io_error(path))
+}
+
+/// Makes the rename durable, by flushing the directory holding `path`
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+/// Directories cannot be opened as files on other platforms
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+impl JsonCanvas {
+    /// Reads and parses a canvas file
+    pub fn load(path: impl AsRef<Path>) -> Result<JsonCanvas, JsonCanvasError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(io_error(path))?;
+        parse::parse(&content).map_err(|source| JsonCanvasError::InvalidFile {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Writes the canvas to a file atomically, keeping backups of the previous
+    /// versions according to `options`
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        options: &SaveOptions,
+    ) -> Result<(), JsonCanvasError> {
+        let path = path.as_ref();
+        rotate_backups(path, options.backups)?;
+        write_atomic(path, &self.to_string_with(&options.format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::GenericNodeInfo;
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("jsoncanvas-io-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.canvas");
+        let options = SaveOptions {
+            backups: 2,
+            ..Default::default()
+        };
+
+        let mut canvas: JsonCanvas =
+            r#"{"nodes":[{"type":"text","id":"a","x":0,"y":0,"width":10,"height":10,"text":"A"}]}"#
+                .parse()
+                .unwrap();
+        for x in 1..=3 {
+            canvas.get_node("a".parse().unwrap()).unwrap().set_x(x);
+            canvas.save(&path, &options).unwrap();
+        }
+        let x = |path: &Path| {
+            JsonCanvas::load(path)
+                .unwrap()
+                .nodes()
+                .next()
+                .unwrap()
+                .get_x()
+        };
+        assert_eq!(x(&path), 3);
+        assert_eq!(x(&backup_path(&path, 0)), 2);
+        assert_eq!(x(&backup_path(&path, 1)), 1);
+        assert!(!backup_path(&path, 2).exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        fs::write(&path, "{").unwrap();
+        let err = JsonCanvas::load(&path).unwrap_err();
+        assert!(matches!(&err, JsonCanvasError::InvalidFile { path: p, .. } if *p == path));
+        let missing = dir.join("missing.canvas");
+        let err = JsonCanvas::load(&missing).unwrap_err();
+        assert!(err.to_string().starts_with(&missing.display().to_string()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_saves() {
+        let dir =
+            std::env::temp_dir().join(format!("jsoncanvas-io-threads-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.canvas");
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        JsonCanvas::default()
+                            .save(&path, &SaveOptions::default())
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(JsonCanvas::load(&path).unwrap(), JsonCanvas::default());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
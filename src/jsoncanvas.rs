@@ -1,14 +1,21 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use crate::edge::Edge;
+use crate::events::{CanvasEvent, EdgeMut, NodeMut, Observers, SubscriptionId};
 use crate::id::EmptyId;
 use crate::node::GenericNodeInfo;
 use crate::node::Node;
+use crate::ordered::OrderedMap;
+use crate::parse::{self, ParseError};
+use crate::patch::PatchConflict;
 use crate::EdgeId;
 use crate::NodeId;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json;
 use thiserror::Error;
@@ -21,32 +28,71 @@ pub enum JsonCanvasError {
     EdgeExists(EdgeId),
     #[error("Node {0} does not exist")]
     NodeNotExists(NodeId),
+    #[error("Edge {0} does not exist")]
+    EdgeNotExists(EdgeId),
+    #[error("Transaction failed at step {step}: {source}")]
+    TransactionFailed {
+        step: usize,
+        source: Box<JsonCanvasError>,
+    },
+    #[error("Patch operation {index} conflicts: {conflict}")]
+    PatchConflict {
+        index: usize,
+        conflict: PatchConflict,
+    },
     #[error(transparent)]
-    ParseError(#[from] serde_json::Error),
+    ParseError(#[from] ParseError),
     #[error(transparent)]
     EmptyId(#[from] EmptyId),
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: {source}", path.display())]
+    InvalidFile { path: PathBuf, source: ParseError },
+    /// Reading or writing a stream failed, see [`crate::stream`]
+    #[error("Stream I/O failed: {0}")]
+    StreamIo(std::io::Error),
+    #[error("Nodes must be written before edges")]
+    NodeAfterEdges,
+    #[error("Canvas changed since version {expected}, now at version {actual}")]
+    VersionMismatch { expected: u64, actual: u64 },
+}
+
+impl From<serde_json::Error> for JsonCanvasError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::ParseError(err.into())
+    }
 }
 
 /// JsonCanvas
 ///
 /// Main struct for the canvas
 ///
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, JsonSchema)]
 pub struct JsonCanvas {
     #[serde(
         serialize_with = "serialize_as_vec_node",
         deserialize_with = "deserialize_as_map_node"
     )]
-    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    nodes: HashMap<NodeId, Node>,
+    #[schemars(with = "Vec<Node>")]
+    #[serde(skip_serializing_if = "OrderedMap::is_empty", default)]
+    nodes: OrderedMap<NodeId, Node>,
     #[serde(
         serialize_with = "serialize_as_vec_edge",
         deserialize_with = "deserialize_as_map_edge"
     )]
-    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
-    edges: HashMap<EdgeId, Edge>,
+    #[schemars(with = "Vec<Edge>")]
+    #[serde(skip_serializing_if = "OrderedMap::is_empty", default)]
+    edges: OrderedMap<EdgeId, Edge>,
+    #[serde(skip)]
+    observers: Observers,
 }
-fn serialize_as_vec_node<S>(data: &HashMap<NodeId, Node>, serializer: S) -> Result<S::Ok, S::Error>
+fn serialize_as_vec_node<S>(
+    data: &OrderedMap<NodeId, Node>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
@@ -54,19 +100,22 @@ where
     vec.serialize(serializer)
 }
 
-fn deserialize_as_map_node<'de, D>(deserializer: D) -> Result<HashMap<NodeId, Node>, D::Error>
+fn deserialize_as_map_node<'de, D>(deserializer: D) -> Result<OrderedMap<NodeId, Node>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let vec: Vec<Node> = Vec::deserialize(deserializer)?;
-    let map: HashMap<_, _> = vec
+    let map: OrderedMap<_, _> = vec
         .into_iter()
         .map(|node| (node.id().clone(), node))
         .collect();
     Ok(map)
 }
 
-fn serialize_as_vec_edge<S>(data: &HashMap<EdgeId, Edge>, serializer: S) -> Result<S::Ok, S::Error>
+fn serialize_as_vec_edge<S>(
+    data: &OrderedMap<EdgeId, Edge>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
@@ -74,12 +123,12 @@ where
     vec.serialize(serializer)
 }
 
-fn deserialize_as_map_edge<'de, D>(deserializer: D) -> Result<HashMap<EdgeId, Edge>, D::Error>
+fn deserialize_as_map_edge<'de, D>(deserializer: D) -> Result<OrderedMap<EdgeId, Edge>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let vec: Vec<Edge> = Vec::deserialize(deserializer)?;
-    let map: HashMap<_, _> = vec
+    let map: OrderedMap<_, _> = vec
         .into_iter()
         .map(|node| (node.id().clone(), node))
         .collect();
@@ -91,6 +140,7 @@ impl JsonCanvas {
         if self.nodes.contains_key(node.id()) {
             return Err(JsonCanvasError::NodeExists(node.id().clone()));
         }
+        self.observers.emit(|| CanvasEvent::NodeAdded(node.clone()));
         self.nodes.insert(node.id().clone(), node);
         Ok(())
     }
@@ -108,32 +158,215 @@ impl JsonCanvas {
             return Err(JsonCanvasError::NodeNotExists(edge.to_node().clone()));
         }
 
+        self.observers.emit(|| CanvasEvent::EdgeAdded(edge.clone()));
         self.edges.insert(edge.id().clone(), edge);
         Ok(())
     }
 
-    pub fn get_node(&mut self, id: NodeId) -> Option<&mut Node> {
-        self.nodes.get_mut(&id)
+    /// Removes a node, along with the edges connected to it
+    pub fn remove_node(&mut self, id: &NodeId) -> Result<(Node, Vec<Edge>), JsonCanvasError> {
+        let (_, node) = self
+            .nodes
+            .remove(id)
+            .ok_or_else(|| JsonCanvasError::NodeNotExists(id.clone()))?;
+
+        let connected: Vec<EdgeId> = self
+            .edges
+            .values()
+            .filter(|edge| edge.from_node() == id || edge.to_node() == id)
+            .map(|edge| edge.id().clone())
+            .collect();
+        let edges: Vec<Edge> = connected
+            .iter()
+            .filter_map(|edge_id| self.edges.remove(edge_id).map(|(_, edge)| edge))
+            .collect();
+
+        for edge in &edges {
+            self.observers
+                .emit(|| CanvasEvent::EdgeRemoved(edge.clone()));
+        }
+        self.observers
+            .emit(|| CanvasEvent::NodeRemoved(node.clone()));
+        Ok((node, edges))
     }
 
-    pub fn get_edge(&mut self, id: EdgeId) -> Option<&mut Edge> {
-        self.edges.get_mut(&id)
+    pub fn remove_edge(&mut self, id: &EdgeId) -> Result<Edge, JsonCanvasError> {
+        let (_, edge) = self
+            .edges
+            .remove(id)
+            .ok_or_else(|| JsonCanvasError::EdgeNotExists(id.clone()))?;
+        self.observers
+            .emit(|| CanvasEvent::EdgeRemoved(edge.clone()));
+        Ok(edge)
     }
 
-    pub fn get_nodes(&self) -> &HashMap<NodeId, Node> {
+    /// Calls `observer` with every change made to the canvas from now on.
+    ///
+    /// The events of a patch, transaction or batch are only sent once all of it
+    /// succeeded. Changes made through the deprecated [`JsonCanvas::get_mut_nodes`]
+    /// and [`JsonCanvas::get_mut_edges`] are not reported.
+    ///
+    /// Observers are not cloned along with the canvas, and are ignored when
+    /// comparing canvases.
+    pub fn subscribe(
+        &mut self,
+        observer: impl Fn(&CanvasEvent) + Send + Sync + 'static,
+    ) -> SubscriptionId {
+        self.observers.subscribe(std::sync::Arc::new(observer))
+    }
+
+    /// Removes an observer, returning false if it was not subscribed
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.observers.unsubscribe(id)
+    }
+
+    pub(crate) fn emit(&self, event: impl FnOnce() -> CanvasEvent) {
+        self.observers.emit(event)
+    }
+
+    pub(crate) fn emit_edge_update(&self, old: &Edge, new: &Edge) {
+        self.observers.emit_edge_update(old, new)
+    }
+
+    /// Counts the changes even without observers, for [`JsonCanvas::changes`]
+    pub(crate) fn set_tracked(&mut self, tracked: bool) {
+        self.observers.set_tracked(tracked)
+    }
+
+    /// Number of changes made to a tracked canvas, which only grows when the canvas
+    /// may have changed
+    pub(crate) fn changes(&self) -> u64 {
+        self.observers.changes()
+    }
+
+    /// Runs `edit`, sending its events to the observers only if it succeeds
+    pub(crate) fn atomically<T, E>(
+        &mut self,
+        edit: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mark = self.observers.hold();
+        match panic::catch_unwind(AssertUnwindSafe(|| edit(self))) {
+            Ok(result) => {
+                self.observers.release(mark, result.is_ok());
+                result
+            }
+            Err(payload) => {
+                self.observers.release(mark, false);
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Nodes, changed without notifying the observers
+    pub(crate) fn unobserved_nodes_mut(&mut self) -> &mut OrderedMap<NodeId, Node> {
+        &mut self.nodes
+    }
+
+    /// Edges, changed without notifying the observers
+    pub(crate) fn unobserved_edges_mut(&mut self) -> &mut OrderedMap<EdgeId, Edge> {
+        &mut self.edges
+    }
+
+    pub(crate) fn ordered_nodes(&self) -> &OrderedMap<NodeId, Node> {
         &self.nodes
     }
 
+    pub(crate) fn ordered_edges(&self) -> &OrderedMap<EdgeId, Edge> {
+        &self.edges
+    }
+
+    pub(crate) fn into_parts(self) -> (OrderedMap<NodeId, Node>, OrderedMap<EdgeId, Edge>) {
+        (self.nodes, self.edges)
+    }
+
+    pub fn node(&self, id: &NodeId) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    pub fn edge(&self, id: &EdgeId) -> Option<&Edge> {
+        self.edges.get(id)
+    }
+
+    /// Same as [`JsonCanvas::node_mut`]
+    pub fn get_node(&mut self, id: NodeId) -> Option<NodeMut<'_>> {
+        self.node_mut(&id)
+    }
+
+    /// Same as [`JsonCanvas::edge_mut`]
+    pub fn get_edge(&mut self, id: EdgeId) -> Option<EdgeMut<'_>> {
+        self.edge_mut(&id)
+    }
+
+    /// Mutable access to a node, reporting its changes to the observers
+    pub fn node_mut(&mut self, id: &NodeId) -> Option<NodeMut<'_>> {
+        let node = self.nodes.get_mut(id)?;
+        Some(NodeMut::new(node, &self.observers))
+    }
+
+    /// Mutable access to an edge, reporting its changes to the observers
+    pub fn edge_mut(&mut self, id: &EdgeId) -> Option<EdgeMut<'_>> {
+        let edge = self.edges.get_mut(id)?;
+        Some(EdgeMut::new(edge, &self.observers))
+    }
+
+    pub fn get_nodes(&self) -> &HashMap<NodeId, Node> {
+        self.nodes.map()
+    }
+
+    /// Nodes added through the map are drawn above the others, sorted by ID.
+    ///
+    /// Changes made through the map are not reported to the observers.
+    #[deprecated(note = "use `node_mut`, `add_node` or `remove_node`, which report their changes")]
     pub fn get_mut_nodes(&mut self) -> &mut HashMap<NodeId, Node> {
-        &mut self.nodes
+        self.observers.touch();
+        self.nodes.map_mut()
     }
 
     pub fn get_edges(&self) -> &HashMap<EdgeId, Edge> {
-        &self.edges
+        self.edges.map()
     }
 
+    /// Changes made through the map are not reported to the observers
+    #[deprecated(note = "use `edge_mut`, `add_edge` or `remove_edge`, which report their changes")]
     pub fn get_mut_edges(&mut self) -> &mut HashMap<EdgeId, Edge> {
-        &mut self.edges
+        self.observers.touch();
+        self.edges.map_mut()
+    }
+
+    /// Nodes in z-order, from the bottom to the top
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> + Clone {
+        self.nodes.values()
+    }
+
+    /// Edges in the order of the document
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> + Clone {
+        self.edges.values()
+    }
+
+    /// Moves a node to `index` in the z-order, or to the top if `index` is past it
+    pub fn move_node(&mut self, id: &NodeId, index: usize) -> Result<(), JsonCanvasError> {
+        let current = self
+            .nodes
+            .get_index_of(id)
+            .ok_or_else(|| JsonCanvasError::NodeNotExists(id.clone()))?;
+        self.nodes.move_to(id, index);
+        if index.min(self.nodes.len() - 1) != current {
+            self.observers.emit(|| CanvasEvent::NodesReordered);
+        }
+        Ok(())
+    }
+
+    /// Moves an edge to `index` in the order of the document
+    pub fn move_edge(&mut self, id: &EdgeId, index: usize) -> Result<(), JsonCanvasError> {
+        let current = self
+            .edges
+            .get_index_of(id)
+            .ok_or_else(|| JsonCanvasError::EdgeNotExists(id.clone()))?;
+        self.edges.move_to(id, index);
+        if index.min(self.edges.len() - 1) != current {
+            self.observers.emit(|| CanvasEvent::EdgesReordered);
+        }
+        Ok(())
     }
 }
 
@@ -141,7 +374,7 @@ impl FromStr for JsonCanvas {
     type Err = JsonCanvasError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(serde_json::from_str(s)?)
+        Ok(parse::parse(s)?)
     }
 }
 
@@ -221,11 +221,35 @@
 pub type PixelCoordinate = i64;
 pub type PixelDimension = u64;
 
+pub mod borrowed;
+pub mod clipboard;
 pub mod color;
+pub mod diff;
+pub mod document;
 pub mod edge;
+pub mod events;
+pub mod format;
+pub mod geometry;
+pub mod history;
 mod id;
+pub mod import;
+pub mod io;
 pub mod jsoncanvas;
+#[cfg(feature = "lint")]
+pub mod lint;
+pub mod merge;
 pub mod node;
+pub mod number;
+mod ordered;
+pub mod overlap;
+pub mod parse;
+pub mod patch;
+pub mod schema;
+pub mod shared;
+pub mod snapshot;
+pub mod stream;
+pub mod transaction;
+pub mod validate;
 
 pub use id::{EdgeId, NodeId};
 pub use jsoncanvas::JsonCanvas;
@@ -392,4 +416,35 @@ mod test {
 
         let _jsoncanvas_deserialized: JsonCanvas = serialized_canvas.parse().unwrap();
     }
+
+    #[test]
+    fn old_background_key() {
+        use super::borrowed::JsonCanvasRef;
+        use super::node::{Background, BackgroundStyle, Node, NodeRef};
+        use super::JsonCanvas;
+        use std::path::PathBuf;
+
+        let old = r#"{"nodes":[{"type":"group","id":"g","x":0,"y":0,"width":10,"height":10,"image":"bg.png","backgroundStyle":"cover"}]}"#;
+        let canvas: JsonCanvas = old.parse().unwrap();
+        let Some(Node::Group(group)) = canvas.nodes().next() else {
+            panic!("expected a group node");
+        };
+        assert_eq!(
+            group.background(),
+            Some(&Background::new(
+                PathBuf::from("bg.png"),
+                Some(BackgroundStyle::Cover)
+            ))
+        );
+        assert_eq!(
+            canvas.to_string(),
+            old.replace("\"image\"", "\"background\"")
+        );
+
+        let view = JsonCanvasRef::parse(old).unwrap();
+        let Some(NodeRef::Group(group)) = view.get_node("g") else {
+            panic!("expected a group node");
+        };
+        assert_eq!(group.background().unwrap().image(), "bg.png");
+    }
 }
@@ -0,0 +1,736 @@
+use std::fmt::{Display, Formatter};
+
+use hex_color::HexColor;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::color::{Color, PresetColor};
+use crate::geometry::Rect;
+use crate::node::{GenericNodeInfo, Node};
+use crate::patch::CanvasPatch;
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId, PixelCoordinate, PixelDimension};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Severity given to a rule in a [`LintConfig`], or `Off` to disable it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn severity(self) -> Option<Severity> {
+        match self {
+            Level::Off => None,
+            Level::Info => Some(Severity::Info),
+            Level::Warning => Some(Severity::Warning),
+            Level::Error => Some(Severity::Error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// Two nodes intersect, see [`JsonCanvas::overlaps`]
+    OverlappingNodes,
+    /// A group contains no node
+    EmptyGroups,
+    /// A node lies outside every group, in a canvas having groups
+    UngroupedNodes,
+    UnlabeledEdges,
+    /// A node is not placed on the grid
+    OffGrid,
+    /// A node has no width or no height
+    ZeroSizedNodes,
+    /// Several link nodes point to the same URL
+    DuplicateLinks,
+    /// An edge goes from a node to the same node
+    SelfLoops,
+    /// A node or edge uses a hex color missing from the allowed palette
+    Palette,
+}
+
+impl Rule {
+    /// Whether [`JsonCanvas::fix`] can fix the diagnostics of this rule. Empty
+    /// groups connected to edges are still left alone.
+    pub fn is_fixable(self) -> bool {
+        matches!(
+            self,
+            Rule::EmptyGroups
+                | Rule::OffGrid
+                | Rule::ZeroSizedNodes
+                | Rule::SelfLoops
+                | Rule::Palette
+        )
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Rule::OverlappingNodes => "overlapping-nodes",
+            Rule::EmptyGroups => "empty-groups",
+            Rule::UngroupedNodes => "ungrouped-nodes",
+            Rule::UnlabeledEdges => "unlabeled-edges",
+            Rule::OffGrid => "off-grid",
+            Rule::ZeroSizedNodes => "zero-sized-nodes",
+            Rule::DuplicateLinks => "duplicate-links",
+            Rule::SelfLoops => "self-loops",
+            Rule::Palette => "palette",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Problem found by a lint rule
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<EdgeId>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}[{}]: {}", severity, self.rule, self.message)
+    }
+}
+
+/// Rules to run, with their severity and settings.
+///
+/// Missing keys take their default value, so a configuration file only needs to
+/// list what it changes:
+///
+/// ```
+/// use jsoncanvas::lint::{Level, LintConfig};
+///
+/// let config = LintConfig::from_toml(r##"
+///     off-grid = "warning"
+///     grid = 20
+///     palette = "error"
+///     allowed-colors = ["#FF0000"]
+/// "##).unwrap();
+///
+/// assert_eq!(config.off_grid, Level::Warning);
+/// assert_eq!(config.overlapping_nodes, Level::Warning);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct LintConfig {
+    pub overlapping_nodes: Level,
+    pub empty_groups: Level,
+    pub ungrouped_nodes: Level,
+    pub unlabeled_edges: Level,
+    pub off_grid: Level,
+    /// Grid size used by the off-grid rule
+    pub grid: PixelDimension,
+    pub zero_sized_nodes: Level,
+    /// Size given by the fix of the zero-sized-nodes rule to an empty dimension,
+    /// which cannot be 0
+    pub default_size: PixelDimension,
+    pub duplicate_links: Level,
+    pub self_loops: Level,
+    pub palette: Level,
+    /// Hex colors accepted by the palette rule, preset colors are always accepted
+    pub allowed_colors: Vec<HexColor>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            overlapping_nodes: Level::Warning,
+            empty_groups: Level::Warning,
+            ungrouped_nodes: Level::Info,
+            unlabeled_edges: Level::Off,
+            off_grid: Level::Off,
+            grid: 20,
+            zero_sized_nodes: Level::Error,
+            default_size: 100,
+            duplicate_links: Level::Warning,
+            self_loops: Level::Warning,
+            palette: Level::Off,
+            allowed_colors: Vec::new(),
+        }
+    }
+}
+
+/// Configuration which could not be read
+#[derive(Debug, thiserror::Error)]
+pub enum LintConfigError {
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("default-size must be greater than 0")]
+    ZeroDefaultSize,
+}
+
+impl LintConfig {
+    pub fn from_toml(s: &str) -> Result<Self, LintConfigError> {
+        toml::from_str::<Self>(s)?.checked()
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, LintConfigError> {
+        serde_json::from_str::<Self>(s)?.checked()
+    }
+
+    fn checked(self) -> Result<Self, LintConfigError> {
+        if self.default_size == 0 {
+            return Err(LintConfigError::ZeroDefaultSize);
+        }
+        Ok(self)
+    }
+
+    pub fn level(&self, rule: Rule) -> Level {
+        match rule {
+            Rule::OverlappingNodes => self.overlapping_nodes,
+            Rule::EmptyGroups => self.empty_groups,
+            Rule::UngroupedNodes => self.ungrouped_nodes,
+            Rule::UnlabeledEdges => self.unlabeled_edges,
+            Rule::OffGrid => self.off_grid,
+            Rule::ZeroSizedNodes => self.zero_sized_nodes,
+            Rule::DuplicateLinks => self.duplicate_links,
+            Rule::SelfLoops => self.self_loops,
+            Rule::Palette => self.palette,
+        }
+    }
+}
+
+const RULES: [Rule; 9] = [
+    Rule::OverlappingNodes,
+    Rule::EmptyGroups,
+    Rule::UngroupedNodes,
+    Rule::UnlabeledEdges,
+    Rule::OffGrid,
+    Rule::ZeroSizedNodes,
+    Rule::DuplicateLinks,
+    Rule::SelfLoops,
+    Rule::Palette,
+];
+
+/// Fixes computed by [`JsonCanvas::fix`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFix {
+    /// Diagnostics fixed by the patch
+    pub diagnostics: Vec<Diagnostic>,
+    pub patch: CanvasPatch,
+}
+
+/// What a rule reports, before a severity is given to it
+struct Finding {
+    message: String,
+    nodes: Vec<NodeId>,
+    edges: Vec<EdgeId>,
+}
+
+impl Finding {
+    fn node(id: &NodeId, message: String) -> Self {
+        Self {
+            message,
+            nodes: vec![id.clone()],
+            edges: Vec::new(),
+        }
+    }
+
+    fn edge(id: &EdgeId, message: String) -> Self {
+        Self {
+            message,
+            nodes: Vec::new(),
+            edges: vec![id.clone()],
+        }
+    }
+}
+
+impl JsonCanvas {
+    /// Runs the enabled rules of `config`, returning their diagnostics by rule, then
+    /// in canvas order
+    pub fn lint(&self, config: &LintConfig) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for rule in RULES {
+            let Some(severity) = config.level(rule).severity() else {
+                continue;
+            };
+            diagnostics.extend(
+                self.check(rule, config)
+                    .into_iter()
+                    .map(|finding| Diagnostic {
+                        rule,
+                        severity,
+                        message: finding.message,
+                        nodes: finding.nodes,
+                        edges: finding.edges,
+                    }),
+            );
+        }
+        diagnostics
+    }
+
+    /// Fixes the diagnostics of the enabled rules that can be fixed.
+    ///
+    /// Fixes snap nodes to the grid, remove self-loops and empty groups without
+    /// edges, give a size to zero-sized nodes and replace hex colors outside the
+    /// palette with the nearest preset. They are returned as a patch to review,
+    /// applied to the canvas unless `dry_run` is set.
+    pub fn fix(&mut self, config: &LintConfig, dry_run: bool) -> Result<LintFix, JsonCanvasError> {
+        let diagnostics: Vec<Diagnostic> = self
+            .lint(config)
+            .into_iter()
+            .filter(|diagnostic| diagnostic.rule.is_fixable())
+            .filter(|diagnostic| {
+                // Removing the group would remove its edges too
+                diagnostic.rule != Rule::EmptyGroups || diagnostic.edges.is_empty()
+            })
+            .collect();
+
+        let mut fixed = self.clone();
+        for diagnostic in &diagnostics {
+            fixed.fix_diagnostic(diagnostic, config);
+        }
+        let patch = CanvasPatch::between(self, &fixed);
+        if !dry_run {
+            self.apply(&patch)?;
+        }
+        Ok(LintFix { diagnostics, patch })
+    }
+
+    fn fix_diagnostic(&mut self, diagnostic: &Diagnostic, config: &LintConfig) {
+        for id in &diagnostic.nodes {
+            match diagnostic.rule {
+                Rule::EmptyGroups => {
+                    // Already gone if removed by an earlier fix
+                    let _ = self.remove_node(id);
+                }
+                _ => {
+                    let Some(mut node) = self.node_mut(id) else {
+                        continue;
+                    };
+                    fix_node(&mut node, diagnostic.rule, config);
+                }
+            }
+        }
+        for id in &diagnostic.edges {
+            match diagnostic.rule {
+                Rule::SelfLoops => {
+                    let _ = self.remove_edge(id);
+                }
+                Rule::Palette => {
+                    if let Some(mut edge) = self.edge_mut(id) {
+                        if let Some(Color::Color(hex)) = edge.color() {
+                            let preset = PresetColor::nearest(*hex);
+                            edge.set_color(preset.into());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check(&self, rule: Rule, config: &LintConfig) -> Vec<Finding> {
+        let nodes = self.nodes();
+        let edges = self.edges();
+        match rule {
+            Rule::OverlappingNodes => self
+                .overlaps()
+                .into_iter()
+                .map(|overlap| Finding {
+                    message: format!("nodes {} and {} overlap", overlap.first, overlap.second),
+                    nodes: vec![overlap.first, overlap.second],
+                    edges: Vec::new(),
+                })
+                .collect(),
+            Rule::EmptyGroups => nodes
+                .filter(|node| matches!(node, Node::Group(_)))
+                .filter(|node| self.group_content(node.id()).is_empty())
+                .map(|node| {
+                    let id = node.id();
+                    let connected: Vec<EdgeId> = self
+                        .edges()
+                        .filter(|edge| edge.from_node() == id || edge.to_node() == id)
+                        .map(|edge| edge.id().clone())
+                        .collect();
+                    let mut message = format!("group {} is empty", id);
+                    if !connected.is_empty() {
+                        let names: Vec<&str> = connected.iter().map(EdgeId::as_str).collect();
+                        message += &format!(" but connected by {}", names.join(", "));
+                    }
+                    Finding {
+                        message,
+                        nodes: vec![id.clone()],
+                        edges: connected,
+                    }
+                })
+                .collect(),
+            Rule::UngroupedNodes => {
+                let groups: Vec<Rect> = nodes
+                    .clone()
+                    .filter(|node| matches!(node, Node::Group(_)))
+                    .map(Rect::of)
+                    .collect();
+                if groups.is_empty() {
+                    return Vec::new();
+                }
+                nodes
+                    .filter(|node| !matches!(node, Node::Group(_)))
+                    .filter(|node| !groups.iter().any(|group| group.contains(&Rect::of(*node))))
+                    .map(|node| {
+                        Finding::node(
+                            node.id(),
+                            format!("node {} is outside any group", node.id()),
+                        )
+                    })
+                    .collect()
+            }
+            Rule::UnlabeledEdges => edges
+                .filter(|edge| edge.label().is_none_or(|label| label.trim().is_empty()))
+                .map(|edge| Finding::edge(edge.id(), format!("edge {} has no label", edge.id())))
+                .collect(),
+            Rule::OffGrid => {
+                let grid = config.grid.max(1) as f64;
+                nodes
+                    .filter(|node| {
+                        node.get_exact_x() % grid != 0.0 || node.get_exact_y() % grid != 0.0
+                    })
+                    .map(|node| {
+                        Finding::node(
+                            node.id(),
+                            format!(
+                                "node {} at ({}, {}) is not on the {} pixel grid",
+                                node.id(),
+                                node.get_exact_x(),
+                                node.get_exact_y(),
+                                grid
+                            ),
+                        )
+                    })
+                    .collect()
+            }
+            Rule::ZeroSizedNodes => nodes
+                .filter(|node| node.get_width() == 0 || node.get_height() == 0)
+                .map(|node| {
+                    Finding::node(
+                        node.id(),
+                        format!(
+                            "node {} has a size of {}x{}",
+                            node.id(),
+                            node.get_width(),
+                            node.get_height()
+                        ),
+                    )
+                })
+                .collect(),
+            Rule::DuplicateLinks => {
+                let mut links: IndexMap<&str, Vec<NodeId>> = IndexMap::new();
+                for node in nodes {
+                    if let Node::Link(link) = node {
+                        links
+                            .entry(link.url().as_str())
+                            .or_default()
+                            .push(node.id().clone());
+                    }
+                }
+                links
+                    .into_iter()
+                    .filter(|(_, ids)| ids.len() > 1)
+                    .map(|(url, ids)| Finding {
+                        message: format!("{} link nodes point to {}", ids.len(), url),
+                        nodes: ids,
+                        edges: Vec::new(),
+                    })
+                    .collect()
+            }
+            Rule::SelfLoops => edges
+                .filter(|edge| edge.from_node() == edge.to_node())
+                .map(|edge| {
+                    Finding::edge(
+                        edge.id(),
+                        format!("edge {} loops on node {}", edge.id(), edge.from_node()),
+                    )
+                })
+                .collect(),
+            Rule::Palette => {
+                let outside = |color: Option<&Color>| match color {
+                    Some(Color::Color(hex)) if !config.allowed_colors.contains(hex) => Some(*hex),
+                    _ => None,
+                };
+                let nodes = nodes.filter_map(|node| {
+                    outside(node.color().as_ref()).map(|hex| {
+                        Finding::node(
+                            node.id(),
+                            format!(
+                                "node {} uses {}, outside the palette",
+                                node.id(),
+                                hex.display_rgb()
+                            ),
+                        )
+                    })
+                });
+                let edges = edges.filter_map(|edge| {
+                    outside(edge.color()).map(|hex| {
+                        Finding::edge(
+                            edge.id(),
+                            format!(
+                                "edge {} uses {}, outside the palette",
+                                edge.id(),
+                                hex.display_rgb()
+                            ),
+                        )
+                    })
+                });
+                nodes.chain(edges).collect()
+            }
+        }
+    }
+}
+
+fn fix_node(node: &mut Node, rule: Rule, config: &LintConfig) {
+    match rule {
+        Rule::OffGrid => {
+            let grid = config.grid.max(1) as f64;
+            let snap = |value: f64| ((value / grid + 0.5).floor() * grid) as PixelCoordinate;
+            node.set_x(snap(node.get_exact_x()));
+            node.set_y(snap(node.get_exact_y()));
+        }
+        Rule::ZeroSizedNodes => {
+            if node.get_width() == 0 {
+                node.set_width(config.default_size.max(1));
+            }
+            if node.get_height() == 0 {
+                node.set_height(config.default_size.max(1));
+            }
+        }
+        Rule::Palette => {
+            if let Some(Color::Color(hex)) = node.color() {
+                let preset = PresetColor::nearest(*hex);
+                node.set_color(Some(preset.into()));
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+    use crate::node::{GroupNode, LinkNode, TextNode};
+    use url::Url;
+
+    fn text(id: &str, x: PixelCoordinate, width: PixelDimension) -> Node {
+        TextNode::new(id.parse().unwrap(), x, 0, width, 100, None, id.to_string()).into()
+    }
+
+    fn link(id: &str, x: PixelCoordinate) -> Node {
+        let url = Url::parse("https://jsoncanvas.org").unwrap();
+        LinkNode::new(id.parse().unwrap(), x, 500, 100, 100, None, url).into()
+    }
+
+    fn rules(diagnostics: &[Diagnostic]) -> Vec<(Rule, Vec<&str>)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let ids = diagnostic.nodes.iter().map(NodeId::as_str);
+                let ids = ids.chain(diagnostic.edges.iter().map(EdgeId::as_str));
+                (diagnostic.rule, ids.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_rules() {
+        let mut canvas = JsonCanvas::default();
+        canvas
+            .add_node(GroupNode::new("g".parse().unwrap(), 0, 0, 300, 300, None, None, None).into())
+            .unwrap();
+        canvas
+            .add_node(GroupNode::new("e".parse().unwrap(), 400, 0, 50, 50, None, None, None).into())
+            .unwrap();
+        canvas.add_node(text("a", 10, 100)).unwrap();
+        canvas.add_node(text("b", 50, 100)).unwrap();
+        canvas.add_node(text("z", 1000, 0)).unwrap();
+        canvas.add_node(link("l1", 0)).unwrap();
+        canvas.add_node(link("l2", 200)).unwrap();
+        canvas
+            .add_edge(Edge::new(
+                "loop".parse().unwrap(),
+                "a".parse().unwrap(),
+                None,
+                None,
+                "a".parse().unwrap(),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+
+        let diagnostics = canvas.lint(&LintConfig::default());
+        assert_eq!(
+            rules(&diagnostics),
+            vec![
+                (Rule::OverlappingNodes, vec!["a", "b"]),
+                (Rule::EmptyGroups, vec!["e"]),
+                (Rule::UngroupedNodes, vec!["z"]),
+                (Rule::UngroupedNodes, vec!["l1"]),
+                (Rule::UngroupedNodes, vec!["l2"]),
+                (Rule::ZeroSizedNodes, vec!["z"]),
+                (Rule::DuplicateLinks, vec!["l1", "l2"]),
+                (Rule::SelfLoops, vec!["loop"]),
+            ]
+        );
+        assert_eq!(
+            diagnostics[5].to_string(),
+            "error[zero-sized-nodes]: node z has a size of 0x100"
+        );
+    }
+
+    #[test]
+    fn configured_rules() {
+        let mut canvas = JsonCanvas::default();
+        let mut node = text("a", 15, 100);
+        node.set_color(Some(HexColor::rgb(1, 2, 3).into()));
+        canvas.add_node(node).unwrap();
+        let mut node = text("b", 200, 100);
+        node.set_color(Some(HexColor::rgb(255, 0, 0).into()));
+        canvas.add_node(node).unwrap();
+
+        let config = LintConfig::from_json(
+            r##"{"off-grid": "error", "grid": 10, "palette": "info", "allowed-colors": ["#FF0000"]}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            rules(&canvas.lint(&config)),
+            vec![(Rule::OffGrid, vec!["a"]), (Rule::Palette, vec!["a"])]
+        );
+
+        assert!(matches!(
+            LintConfig::from_toml("unknown = \"off\""),
+            Err(LintConfigError::Toml(_))
+        ));
+        assert!(matches!(
+            LintConfig::from_json(r#"{"default-size": 0}"#),
+            Err(LintConfigError::ZeroDefaultSize)
+        ));
+    }
+
+    #[test]
+    fn off_grid_floats() {
+        let mut canvas: JsonCanvas = r#"{"nodes":[{"type":"text","id":"a","x":10.4,"y":20,"width":10,"height":10,"text":"a"}]}"#
+            .parse()
+            .unwrap();
+        let config = LintConfig {
+            off_grid: Level::Warning,
+            grid: 10,
+            ungrouped_nodes: Level::Off,
+            ..LintConfig::default()
+        };
+        let diagnostics = canvas.lint(&config);
+        assert_eq!(
+            diagnostics[0].message,
+            "node a at (10.4, 20) is not on the 10 pixel grid"
+        );
+
+        canvas.fix(&config, false).unwrap();
+        assert!(canvas.lint(&config).is_empty());
+        assert!(canvas.to_string().contains(r#""x":10,"y":20"#));
+    }
+
+    #[test]
+    fn fixes() {
+        let mut canvas = JsonCanvas::default();
+        canvas
+            .add_node(GroupNode::new("g".parse().unwrap(), 0, 0, 50, 50, None, None, None).into())
+            .unwrap();
+        let mut node = text("a", 113, 0);
+        node.set_color(Some(HexColor::rgb(250, 0, 0).into()));
+        canvas.add_node(node).unwrap();
+        canvas
+            .add_edge(Edge::new(
+                "loop".parse().unwrap(),
+                "a".parse().unwrap(),
+                None,
+                None,
+                "a".parse().unwrap(),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+        let config = LintConfig::from_toml(
+            "off-grid = \"warning\"\npalette = \"warning\"\nungrouped-nodes = \"off\"",
+        )
+        .unwrap();
+
+        let original = canvas.clone();
+        let preview = canvas.fix(&config, true).unwrap();
+        assert_eq!(canvas, original);
+        assert_eq!(preview.diagnostics.len(), 5);
+
+        let fix = canvas.fix(&config, false).unwrap();
+        assert_eq!(fix, preview);
+        assert!(canvas.lint(&config).is_empty());
+        assert!(canvas.get_edges().is_empty());
+        let node = canvas.nodes().next().unwrap();
+        assert_eq!(Rect::of(node), Rect::new(120, 0, 100, 100));
+        assert_eq!(node.color(), &Some(PresetColor::Red.into()));
+
+        canvas.apply(&fix.patch.invert()).unwrap();
+        assert_eq!(canvas, original);
+    }
+
+    #[test]
+    fn empty_groups_with_edges_are_kept() {
+        let mut canvas = JsonCanvas::default();
+        canvas
+            .add_node(GroupNode::new("g".parse().unwrap(), 0, 0, 50, 50, None, None, None).into())
+            .unwrap();
+        canvas.add_node(text("a", 100, 100)).unwrap();
+        canvas
+            .add_edge(Edge::new(
+                "ga".parse().unwrap(),
+                "g".parse().unwrap(),
+                None,
+                None,
+                "a".parse().unwrap(),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+
+        let diagnostics = canvas.lint(&LintConfig::default());
+        let empty = diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.rule == Rule::EmptyGroups)
+            .unwrap();
+        assert_eq!(empty.edges, vec!["ga".parse::<EdgeId>().unwrap()]);
+        assert_eq!(empty.message, "group g is empty but connected by ga");
+
+        let original = canvas.clone();
+        let fix = canvas.fix(&LintConfig::default(), false).unwrap();
+        assert!(fix.diagnostics.is_empty());
+        assert_eq!(canvas, original);
+    }
+}
@@ -0,0 +1,582 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::ordered::OrderedMap;
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId};
+
+/// Value kept when both sides changed the same field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictPolicy {
+    #[default]
+    Ours,
+    Theirs,
+    Base,
+}
+
+/// What to do with an item deleted on one side and modified on the other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeletePolicy {
+    /// Keep the modified item
+    #[default]
+    Keep,
+    /// Honor the deletion
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergeOptions {
+    pub policy: ConflictPolicy,
+    pub delete_policy: DeletePolicy,
+}
+
+/// Side of a three-way merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeSide {
+    Ours,
+    Theirs,
+}
+
+/// A change that could not be merged automatically, and was resolved according to
+/// the [`MergeOptions`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "conflict", rename_all = "camelCase")]
+pub enum Conflict {
+    /// Both sides changed the same field of a node
+    NodeField {
+        id: NodeId,
+        field: String,
+        ours: Option<Value>,
+        theirs: Option<Value>,
+    },
+    EdgeField {
+        id: EdgeId,
+        field: String,
+        ours: Option<Value>,
+        theirs: Option<Value>,
+    },
+    /// One side deleted the node while the other modified it
+    NodeDeleted {
+        id: NodeId,
+        deleted_by: MergeSide,
+    },
+    EdgeDeleted {
+        id: EdgeId,
+        deleted_by: MergeSide,
+    },
+    /// The edge was dropped because one of its nodes is not in the merged canvas
+    EdgeDangling {
+        id: EdgeId,
+    },
+    /// Both sides added the node with fields that cannot be combined, and there is
+    /// no base version to fall back to under [`ConflictPolicy::Base`]: ours is kept
+    NodeUnresolved {
+        id: NodeId,
+    },
+    EdgeUnresolved {
+        id: EdgeId,
+    },
+}
+
+/// Outcome of [`JsonCanvas::merge3`]
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    pub canvas: JsonCanvas,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+impl JsonCanvas {
+    /// Merges the changes made by `ours` and `theirs` to their common ancestor `base`.
+    ///
+    /// Nodes and edges are merged field by field. Conflicting changes are resolved
+    /// according to `options` and reported in the result. Edges pointing to a node
+    /// missing from the merged canvas are dropped, so the output always passes the
+    /// checks of [`JsonCanvas::add_edge`].
+    pub fn merge3(
+        base: &JsonCanvas,
+        ours: &JsonCanvas,
+        theirs: &JsonCanvas,
+        options: &MergeOptions,
+    ) -> MergeResult {
+        let mut conflicts = Vec::new();
+        let mut canvas = JsonCanvas::default();
+
+        let nodes = merge_items(
+            base.ordered_nodes(),
+            ours.ordered_nodes(),
+            theirs.ordered_nodes(),
+            options,
+            &mut conflicts,
+        );
+        for node in nodes {
+            canvas.add_node(node).expect("merged nodes have unique IDs");
+        }
+
+        let edges = merge_items(
+            base.ordered_edges(),
+            ours.ordered_edges(),
+            theirs.ordered_edges(),
+            options,
+            &mut conflicts,
+        );
+        for edge in edges {
+            let id = edge.id().clone();
+            match canvas.add_edge(edge) {
+                Ok(()) => {}
+                Err(JsonCanvasError::NodeNotExists(_)) => {
+                    conflicts.push(Conflict::EdgeDangling { id })
+                }
+                Err(err) => unreachable!("merged edges have unique IDs: {}", err),
+            }
+        }
+
+        MergeResult { canvas, conflicts }
+    }
+}
+
+/// IDs of the items merged by [`merge_items`], building the matching conflicts
+trait MergeId: Hash + Eq + Ord + Clone {
+    fn field_conflict(&self, field: String, ours: Option<Value>, theirs: Option<Value>)
+        -> Conflict;
+    fn delete_conflict(&self, deleted_by: MergeSide) -> Conflict;
+    fn unresolved_conflict(&self) -> Conflict;
+}
+
+impl MergeId for NodeId {
+    fn field_conflict(
+        &self,
+        field: String,
+        ours: Option<Value>,
+        theirs: Option<Value>,
+    ) -> Conflict {
+        Conflict::NodeField {
+            id: self.clone(),
+            field,
+            ours,
+            theirs,
+        }
+    }
+
+    fn delete_conflict(&self, deleted_by: MergeSide) -> Conflict {
+        Conflict::NodeDeleted {
+            id: self.clone(),
+            deleted_by,
+        }
+    }
+
+    fn unresolved_conflict(&self) -> Conflict {
+        Conflict::NodeUnresolved { id: self.clone() }
+    }
+}
+
+impl MergeId for EdgeId {
+    fn field_conflict(
+        &self,
+        field: String,
+        ours: Option<Value>,
+        theirs: Option<Value>,
+    ) -> Conflict {
+        Conflict::EdgeField {
+            id: self.clone(),
+            field,
+            ours,
+            theirs,
+        }
+    }
+
+    fn delete_conflict(&self, deleted_by: MergeSide) -> Conflict {
+        Conflict::EdgeDeleted {
+            id: self.clone(),
+            deleted_by,
+        }
+    }
+
+    fn unresolved_conflict(&self) -> Conflict {
+        Conflict::EdgeUnresolved { id: self.clone() }
+    }
+}
+
+fn merge_items<K, V>(
+    base: &OrderedMap<K, V>,
+    ours: &OrderedMap<K, V>,
+    theirs: &OrderedMap<K, V>,
+    options: &MergeOptions,
+    conflicts: &mut Vec<Conflict>,
+) -> Vec<V>
+where
+    K: MergeId,
+    V: Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    // Keep the order of our side, followed by the items only added by theirs
+    let ids = ours
+        .keys()
+        .into_iter()
+        .chain(
+            theirs
+                .keys()
+                .into_iter()
+                .filter(|id| !ours.contains_key(id)),
+        )
+        .chain(
+            base.keys()
+                .into_iter()
+                .filter(|id| !ours.contains_key(id) && !theirs.contains_key(id)),
+        );
+
+    let mut merged = Vec::new();
+    for id in ids {
+        let item = match (base.get(id), ours.get(id), theirs.get(id)) {
+            (_, Some(o), Some(t)) if o == t => Some(o.clone()),
+            (None, Some(o), None) => Some(o.clone()),
+            (None, None, Some(t)) => Some(t.clone()),
+            (Some(_), None, None) => None,
+            (Some(b), None, Some(t)) | (Some(b), Some(t), None) if b == t => None,
+            (Some(_), None, Some(kept)) | (Some(_), Some(kept), None) => {
+                let deleted_by = if ours.contains_key(id) {
+                    MergeSide::Theirs
+                } else {
+                    MergeSide::Ours
+                };
+                conflicts.push(id.delete_conflict(deleted_by));
+                match options.delete_policy {
+                    DeletePolicy::Keep => Some(kept.clone()),
+                    DeletePolicy::Delete => None,
+                }
+            }
+            (b, Some(o), Some(t)) => {
+                let merged = merge_fields(b, o, t, options.policy, &mut |field, ours, theirs| {
+                    conflicts.push(id.field_conflict(field, ours, theirs))
+                });
+                Some(merged.unwrap_or_else(|kept| {
+                    conflicts.push(id.unresolved_conflict());
+                    kept
+                }))
+            }
+            (None, None, None) => unreachable!(),
+        };
+        merged.extend(item);
+    }
+    merged
+}
+
+/// Merges an item field by field, or returns ours as an error if the conflict
+/// cannot be resolved according to `policy`
+fn merge_fields<V>(
+    base: Option<&V>,
+    ours: &V,
+    theirs: &V,
+    policy: ConflictPolicy,
+    conflict: &mut dyn FnMut(String, Option<Value>, Option<Value>),
+) -> Result<V, V>
+where
+    V: Clone + Serialize + DeserializeOwned,
+{
+    let base_map = base.map(to_map).unwrap_or_default();
+    let (ours_map, theirs_map) = (to_map(ours), to_map(theirs));
+
+    let keys: BTreeSet<&String> = base_map
+        .keys()
+        .chain(ours_map.keys())
+        .chain(theirs_map.keys())
+        .collect();
+    let mut merged = Map::new();
+    let mut field_conflicts = Vec::new();
+    for key in keys {
+        let (b, o, t) = (base_map.get(key), ours_map.get(key), theirs_map.get(key));
+        let value = if o == t || t == b {
+            o
+        } else if o == b {
+            t
+        } else {
+            field_conflicts.push((key.clone(), o.cloned(), t.cloned()));
+            match policy {
+                ConflictPolicy::Ours => o,
+                ConflictPolicy::Theirs => t,
+                ConflictPolicy::Base => b,
+            }
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+
+    let item = serde_json::from_value(Value::Object(merged));
+    // The fields taken from each side do not make a valid item, typically when one
+    // side changed the type of a node: keep a whole item instead, and report every
+    // field telling the sides apart if no single field conflicted
+    if item.is_err() && field_conflicts.is_empty() {
+        let fields: BTreeSet<&String> = ours_map.keys().chain(theirs_map.keys()).collect();
+        field_conflicts = fields
+            .into_iter()
+            .filter(|field| ours_map.get(*field) != theirs_map.get(*field))
+            .map(|field| {
+                let (o, t) = (ours_map.get(field), theirs_map.get(field));
+                (field.clone(), o.cloned(), t.cloned())
+            })
+            .collect();
+    }
+    for (field, ours, theirs) in field_conflicts {
+        conflict(field, ours, theirs);
+    }
+
+    match (item, policy, base) {
+        (Ok(item), _, _) => Ok(item),
+        (Err(_), ConflictPolicy::Ours, _) => Ok(ours.clone()),
+        (Err(_), ConflictPolicy::Theirs, _) => Ok(theirs.clone()),
+        (Err(_), ConflictPolicy::Base, Some(base)) => Ok(base.clone()),
+        (Err(_), ConflictPolicy::Base, None) => Err(ours.clone()),
+    }
+}
+
+fn to_map<V: Serialize>(item: &V) -> Map<String, Value> {
+    match serde_json::to_value(item) {
+        Ok(Value::Object(map)) => map,
+        _ => unreachable!("nodes and edges serialize as JSON objects"),
+    }
+}
+
+impl Display for MergeSide {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeSide::Ours => write!(f, "ours"),
+            MergeSide::Theirs => write!(f, "theirs"),
+        }
+    }
+}
+
+fn value_str(value: &Option<Value>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "(unset)".to_string(),
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Conflict::NodeField {
+                id,
+                field,
+                ours,
+                theirs,
+            } => write!(
+                f,
+                "node {}: field {} changed on both sides (ours: {}, theirs: {})",
+                id,
+                field,
+                value_str(ours),
+                value_str(theirs)
+            ),
+            Conflict::EdgeField {
+                id,
+                field,
+                ours,
+                theirs,
+            } => write!(
+                f,
+                "edge {}: field {} changed on both sides (ours: {}, theirs: {})",
+                id,
+                field,
+                value_str(ours),
+                value_str(theirs)
+            ),
+            Conflict::NodeDeleted { id, deleted_by } => write!(
+                f,
+                "node {}: deleted by {} but modified on the other side",
+                id, deleted_by
+            ),
+            Conflict::EdgeDeleted { id, deleted_by } => write!(
+                f,
+                "edge {}: deleted by {} but modified on the other side",
+                id, deleted_by
+            ),
+            Conflict::EdgeDangling { id } => {
+                write!(f, "edge {}: dropped, as one of its nodes was deleted", id)
+            }
+            Conflict::NodeUnresolved { id } => write!(
+                f,
+                "node {}: added on both sides in incompatible ways, ours was kept",
+                id
+            ),
+            Conflict::EdgeUnresolved { id } => write!(
+                f,
+                "edge {}: added on both sides in incompatible ways, ours was kept",
+                id
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::PresetColor;
+    use crate::edge::Edge;
+    use crate::node::{FileNode, GenericNodeInfo, LinkNode, Node, TextNode};
+
+    fn text(id: &str, x: i64, text: &str) -> Node {
+        TextNode::new(id.parse().unwrap(), x, 0, 100, 100, None, text.to_string()).into()
+    }
+
+    fn edge(id: &str, from: &str, to: &str) -> Edge {
+        Edge::new(
+            id.parse().unwrap(),
+            from.parse().unwrap(),
+            None,
+            None,
+            to.parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn base() -> JsonCanvas {
+        let mut canvas = JsonCanvas::default();
+        canvas.add_node(text("a", 0, "a")).unwrap();
+        canvas.add_node(text("b", 0, "b")).unwrap();
+        canvas.add_edge(edge("ab", "a", "b")).unwrap();
+        canvas
+    }
+
+    #[test]
+    fn merges_independent_fields() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.get_node("a".parse().unwrap()).unwrap().set_x(50);
+        let mut theirs = base.clone();
+        theirs
+            .edge_mut(&"ab".parse().unwrap())
+            .unwrap()
+            .set_color(PresetColor::Red.into());
+        *theirs.get_node("a".parse().unwrap()).unwrap() = text("a", 0, "A");
+
+        let result = JsonCanvas::merge3(&base, &ours, &theirs, &MergeOptions::default());
+
+        assert!(result.is_clean());
+        let mut expected = base.clone();
+        *expected.get_node("a".parse().unwrap()).unwrap() = text("a", 50, "A");
+        expected
+            .edge_mut(&"ab".parse().unwrap())
+            .unwrap()
+            .set_color(PresetColor::Red.into());
+        assert_eq!(result.canvas, expected);
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.get_node("a".parse().unwrap()).unwrap().set_x(50);
+        ours.remove_node(&"b".parse().unwrap()).unwrap();
+        let mut theirs = base.clone();
+        theirs.get_node("a".parse().unwrap()).unwrap().set_x(70);
+
+        let options = MergeOptions {
+            policy: ConflictPolicy::Theirs,
+            ..Default::default()
+        };
+        let result = JsonCanvas::merge3(&base, &ours, &theirs, &options);
+
+        assert_eq!(
+            result.conflicts,
+            vec![Conflict::NodeField {
+                id: "a".parse().unwrap(),
+                field: "x".to_string(),
+                ours: Some(50.into()),
+                theirs: Some(70.into()),
+            }]
+        );
+        assert_eq!(result.canvas.nodes().next().unwrap().get_x(), 70);
+        assert_eq!(result.canvas.get_nodes().len(), 1);
+        assert!(result.canvas.get_edges().is_empty());
+    }
+
+    fn file(id: &str) -> Node {
+        FileNode::new(
+            id.parse().unwrap(),
+            0,
+            0,
+            100,
+            100,
+            None,
+            "a.md".into(),
+            None,
+        )
+        .into()
+    }
+
+    fn link(id: &str) -> Node {
+        let url = "https://example.com/".parse().unwrap();
+        LinkNode::new(id.parse().unwrap(), 0, 0, 100, 100, None, url).into()
+    }
+
+    fn type_conflict(id: &str) -> Conflict {
+        Conflict::NodeField {
+            id: id.parse().unwrap(),
+            field: "type".to_string(),
+            ours: Some("file".into()),
+            theirs: Some("link".into()),
+        }
+    }
+
+    #[test]
+    fn incompatible_changes_fall_back_to_base() {
+        let base = base();
+        let mut ours = base.clone();
+        *ours.get_node("a".parse().unwrap()).unwrap() = file("a");
+        let mut theirs = base.clone();
+        *theirs.get_node("a".parse().unwrap()).unwrap() = link("a");
+
+        let options = MergeOptions {
+            policy: ConflictPolicy::Base,
+            ..Default::default()
+        };
+        let result = JsonCanvas::merge3(&base, &ours, &theirs, &options);
+
+        // Taking the base type without the text removed by both sides is not a
+        // valid node, so the whole base node is kept
+        assert_eq!(result.conflicts, vec![type_conflict("a")]);
+        assert_eq!(result.canvas, base);
+    }
+
+    #[test]
+    fn incompatible_additions_are_unresolved() {
+        let base = base();
+        let mut ours = base.clone();
+        ours.add_node(file("c")).unwrap();
+        let mut theirs = base.clone();
+        theirs.add_node(link("c")).unwrap();
+
+        let options = MergeOptions {
+            policy: ConflictPolicy::Base,
+            ..Default::default()
+        };
+        let result = JsonCanvas::merge3(&base, &ours, &theirs, &options);
+
+        assert_eq!(
+            result.conflicts,
+            vec![
+                type_conflict("c"),
+                Conflict::NodeUnresolved {
+                    id: "c".parse().unwrap()
+                }
+            ]
+        );
+        assert_eq!(result.canvas, ours);
+    }
+}
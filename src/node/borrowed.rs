@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer};
+use url::Url;
+
+use crate::color::Color;
+use crate::number::{Coordinate, Dimension};
+use crate::{NodeId, PixelCoordinate, PixelDimension};
+
+use super::{
+    Background, BackgroundStyle, FileNode, GenericNode, GroupNode, LinkNode, Node, TextNode,
+};
+
+/// Borrows a string from the input when it has no escape sequence
+fn cow_str<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cow<'de, str>, D::Error> {
+    struct CowVisitor;
+
+    impl<'de> Visitor<'de> for CowVisitor {
+        type Value = Cow<'de, str>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a string")
+        }
+
+        fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
+            Ok(Cow::Borrowed(v))
+        }
+
+        fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v.to_string()))
+        }
+
+        fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+            Ok(Cow::Owned(v))
+        }
+    }
+
+    deserializer.deserialize_str(CowVisitor)
+}
+
+/// `Option<Cow<str>>` would always copy, see serde-rs/serde#1497
+pub(crate) fn optional_cow_str<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Cow<'de, str>>, D::Error> {
+    cow_str(deserializer).map(Some)
+}
+
+/// Checks the URL when reading, so that converting to a [`LinkNode`] cannot fail
+fn url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cow<'de, str>, D::Error> {
+    let url = cow_str(deserializer)?;
+    Url::parse(&url)
+        .map_err(|err| D::Error::custom(format!("invalid URL \"{}\": {}", url, err)))?;
+    Ok(url)
+}
+
+/// Fields common to every [`NodeRef`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GenericNodeRef<'a> {
+    #[serde(borrow)]
+    id: Cow<'a, str>,
+    x: Coordinate,
+    y: Coordinate,
+    width: Dimension,
+    height: Dimension,
+    color: Option<Color>,
+}
+
+impl GenericNodeRef<'_> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_x(&self) -> PixelCoordinate {
+        self.x.get()
+    }
+
+    pub fn get_y(&self) -> PixelCoordinate {
+        self.y.get()
+    }
+
+    pub fn get_width(&self) -> PixelDimension {
+        self.width.get()
+    }
+
+    pub fn get_height(&self) -> PixelDimension {
+        self.height.get()
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+}
+
+impl From<GenericNodeRef<'_>> for GenericNode {
+    fn from(node: GenericNodeRef<'_>) -> Self {
+        GenericNode {
+            id: NodeId::new_unchecked(node.id.into_owned()),
+            x: node.x,
+            y: node.y,
+            width: node.width,
+            height: node.height,
+            color: node.color,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TextNodeRef<'a> {
+    #[serde(flatten, borrow)]
+    generic: GenericNodeRef<'a>,
+    #[serde(borrow)]
+    text: Cow<'a, str>,
+}
+
+impl TextNodeRef<'_> {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<TextNodeRef<'_>> for TextNode {
+    fn from(node: TextNodeRef<'_>) -> Self {
+        TextNode {
+            generic: node.generic.into(),
+            text: node.text.into_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FileNodeRef<'a> {
+    #[serde(flatten, borrow)]
+    generic: GenericNodeRef<'a>,
+    #[serde(borrow)]
+    file: Cow<'a, str>,
+    #[serde(default, borrow, deserialize_with = "optional_cow_str")]
+    subpath: Option<Cow<'a, str>>,
+}
+
+impl FileNodeRef<'_> {
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn subpath(&self) -> Option<&str> {
+        self.subpath.as_deref()
+    }
+}
+
+impl From<FileNodeRef<'_>> for FileNode {
+    fn from(node: FileNodeRef<'_>) -> Self {
+        FileNode {
+            generic: node.generic.into(),
+            file: PathBuf::from(node.file.into_owned()),
+            subpath: node.subpath.map(Cow::into_owned),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LinkNodeRef<'a> {
+    #[serde(flatten, borrow)]
+    generic: GenericNodeRef<'a>,
+    #[serde(borrow, deserialize_with = "url")]
+    url: Cow<'a, str>,
+}
+
+impl LinkNodeRef<'_> {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl From<LinkNodeRef<'_>> for LinkNode {
+    fn from(node: LinkNodeRef<'_>) -> Self {
+        LinkNode {
+            generic: node.generic.into(),
+            url: Url::parse(&node.url).expect("checked when reading"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct GroupNodeRef<'a> {
+    #[serde(flatten, borrow)]
+    generic: GenericNodeRef<'a>,
+    #[serde(default, borrow, deserialize_with = "optional_cow_str")]
+    label: Option<Cow<'a, str>>,
+    #[serde(flatten, borrow)]
+    background: Option<BackgroundRef<'a>>,
+}
+
+impl<'a> GroupNodeRef<'a> {
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn background(&self) -> Option<&BackgroundRef<'a>> {
+        self.background.as_ref()
+    }
+}
+
+impl From<GroupNodeRef<'_>> for GroupNode {
+    fn from(node: GroupNodeRef<'_>) -> Self {
+        GroupNode {
+            generic: node.generic.into(),
+            label: node.label.map(Cow::into_owned),
+            background: node.background.map(|background| Background {
+                image: PathBuf::from(background.image.into_owned()),
+                background_style: background.background_style,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundRef<'a> {
+    #[serde(rename = "background", alias = "image", borrow)]
+    image: Cow<'a, str>,
+    background_style: Option<BackgroundStyle>,
+}
+
+impl BackgroundRef<'_> {
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    pub fn background_style(&self) -> Option<BackgroundStyle> {
+        self.background_style
+    }
+}
+
+/// Node borrowing its strings from the parsed text. See
+/// [`crate::borrowed::JsonCanvasRef`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NodeRef<'a> {
+    Text(#[serde(borrow)] TextNodeRef<'a>),
+    File(#[serde(borrow)] FileNodeRef<'a>),
+    Link(#[serde(borrow)] LinkNodeRef<'a>),
+    Group(#[serde(borrow)] GroupNodeRef<'a>),
+}
+
+impl<'a> NodeRef<'a> {
+    pub fn generic(&self) -> &GenericNodeRef<'a> {
+        match self {
+            NodeRef::Text(node) => &node.generic,
+            NodeRef::File(node) => &node.generic,
+            NodeRef::Link(node) => &node.generic,
+            NodeRef::Group(node) => &node.generic,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        self.generic().id()
+    }
+}
+
+impl From<NodeRef<'_>> for Node {
+    fn from(node: NodeRef<'_>) -> Self {
+        match node {
+            NodeRef::Text(node) => Node::Text(node.into()),
+            NodeRef::File(node) => Node::File(node.into()),
+            NodeRef::Link(node) => Node::Link(node.into()),
+            NodeRef::Group(node) => Node::Group(node.into()),
+        }
+    }
+}
@@ -6,16 +6,18 @@ use crate::{color::Color, PixelCoordinate, PixelDimension};
 use super::ambassador_impl_GenericNodeInfo;
 use super::{GenericNode, GenericNodeInfo};
 use ambassador::Delegate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Delegate, Serialize, Deserialize)]
+#[derive(Debug, Clone, Delegate, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[delegate(GenericNodeInfo, target = "generic")]
 pub struct FileNode {
     #[serde(flatten)]
-    generic: GenericNode,
-    file: PathBuf,
+    pub(super) generic: GenericNode,
+    pub(super) file: PathBuf,
     #[serde(skip_serializing_if = "Option::is_none")]
-    subpath: Option<String>,
+    #[schemars(regex(pattern = "^#"))]
+    pub(super) subpath: Option<String>,
 }
 
 impl FileNode {
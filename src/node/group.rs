@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use ambassador::Delegate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::NodeId;
@@ -9,16 +10,16 @@ use crate::{color::Color, PixelCoordinate, PixelDimension};
 use super::ambassador_impl_GenericNodeInfo;
 use super::{GenericNode, GenericNodeInfo};
 
-#[derive(Debug, Delegate, Serialize, Deserialize)]
+#[derive(Debug, Clone, Delegate, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[delegate(GenericNodeInfo, target = "generic")]
 pub struct GroupNode {
     #[serde(flatten)]
-    generic: GenericNode,
+    pub(super) generic: GenericNode,
     #[serde(skip_serializing_if = "Option::is_none")]
-    label: Option<String>,
+    pub(super) label: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(flatten)]
-    background: Option<Background>,
+    pub(super) background: Option<Background>,
 }
 
 impl GroupNode {
@@ -49,12 +50,14 @@ impl GroupNode {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Background {
-    image: PathBuf,
+    /// Written as `image` by older versions of this crate
+    #[serde(rename = "background", alias = "image")]
+    pub(super) image: PathBuf,
     #[serde(skip_serializing_if = "Option::is_none")]
-    background_style: Option<BackgroundStyle>,
+    pub(super) background_style: Option<BackgroundStyle>,
 }
 
 impl Background {
@@ -66,7 +69,7 @@ impl Background {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum BackgroundStyle {
     Cover,
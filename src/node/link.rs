@@ -1,4 +1,5 @@
 use ambassador::Delegate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 pub use url::Url;
 
@@ -8,12 +9,13 @@ use crate::{color::Color, PixelCoordinate, PixelDimension};
 use super::ambassador_impl_GenericNodeInfo;
 use super::{GenericNode, GenericNodeInfo};
 
-#[derive(Debug, Delegate, Serialize, Deserialize)]
+#[derive(Debug, Clone, Delegate, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[delegate(GenericNodeInfo, target = "generic")]
 pub struct LinkNode {
     #[serde(flatten)]
-    generic: GenericNode,
-    url: Url,
+    pub(super) generic: GenericNode,
+    #[schemars(with = "String", url)]
+    pub(super) url: Url,
 }
 
 impl LinkNode {
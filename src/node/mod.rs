@@ -1,27 +1,34 @@
 use crate::color::Color;
+use crate::number::{Coordinate, Dimension, NumberMode};
 use crate::NodeId;
 use crate::PixelCoordinate;
 use crate::PixelDimension;
 use ambassador::{delegatable_trait, Delegate};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+mod borrowed;
 mod file;
 mod group;
 mod link;
 mod text;
 
+pub(crate) use borrowed::optional_cow_str;
+pub use borrowed::{
+    BackgroundRef, FileNodeRef, GenericNodeRef, GroupNodeRef, LinkNodeRef, NodeRef, TextNodeRef,
+};
 pub use file::FileNode;
 pub use group::{Background, BackgroundStyle, GroupNode};
 pub use link::LinkNode;
 pub use text::TextNode;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct GenericNode {
     pub id: NodeId,
-    x: PixelCoordinate,
-    y: PixelCoordinate,
-    width: PixelDimension,
-    height: PixelDimension,
+    x: Coordinate,
+    y: Coordinate,
+    width: Dimension,
+    height: Dimension,
     #[serde(skip_serializing_if = "Option::is_none")]
     color: Option<crate::color::Color>,
 }
@@ -37,10 +44,10 @@ impl GenericNode {
     ) -> Self {
         Self {
             id,
-            x,
-            y,
-            width,
-            height,
+            x: x.into(),
+            y: y.into(),
+            width: width.into(),
+            height: height.into(),
             color,
         }
     }
@@ -66,19 +73,19 @@ impl GenericNodeInfo for GenericNode {
     }
 
     fn get_x(&self) -> PixelCoordinate {
-        self.x
+        self.x.get()
     }
 
     fn get_y(&self) -> PixelCoordinate {
-        self.y
+        self.y.get()
     }
 
     fn get_width(&self) -> PixelDimension {
-        self.width
+        self.width.get()
     }
 
     fn get_height(&self) -> PixelDimension {
-        self.height
+        self.height.get()
     }
 
     fn color(&self) -> &Option<Color> {
@@ -86,7 +93,7 @@ impl GenericNodeInfo for GenericNode {
     }
 }
 
-#[derive(Debug, Delegate, Serialize, Deserialize)]
+#[derive(Debug, Clone, Delegate, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[delegate(GenericNodeInfo)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Node {
@@ -96,6 +103,86 @@ pub enum Node {
     Group(GroupNode),
 }
 
+impl Node {
+    fn generic(&self) -> &GenericNode {
+        match self {
+            Node::Text(node) => &node.generic,
+            Node::File(node) => &node.generic,
+            Node::Link(node) => &node.generic,
+            Node::Group(node) => &node.generic,
+        }
+    }
+
+    fn generic_mut(&mut self) -> &mut GenericNode {
+        match self {
+            Node::Text(node) => &mut node.generic,
+            Node::File(node) => &mut node.generic,
+            Node::Link(node) => &mut node.generic,
+            Node::Group(node) => &mut node.generic,
+        }
+    }
+
+    /// Changes the ID, which must not be done while the node is in a canvas
+    pub(crate) fn set_id(&mut self, id: NodeId) {
+        self.generic_mut().id = id;
+    }
+
+    pub fn set_x(&mut self, x: PixelCoordinate) {
+        self.generic_mut().x = x.into();
+    }
+
+    pub fn set_y(&mut self, y: PixelCoordinate) {
+        self.generic_mut().y = y.into();
+    }
+
+    pub fn set_width(&mut self, width: PixelDimension) {
+        self.generic_mut().width = width.into();
+    }
+
+    pub fn set_height(&mut self, height: PixelDimension) {
+        self.generic_mut().height = height.into();
+    }
+
+    pub fn set_color(&mut self, color: Option<Color>) {
+        self.generic_mut().color = color;
+    }
+
+    /// Moves the node by `(dx, dy)`, keeping the fractional part of float coordinates
+    pub(crate) fn translate(&mut self, dx: PixelCoordinate, dy: PixelCoordinate) {
+        let generic = self.generic_mut();
+        generic.x = generic.x.offset(dx);
+        generic.y = generic.y.offset(dy);
+    }
+
+    /// Exact value of the x coordinate, which may be a float read from a file
+    pub fn get_exact_x(&self) -> f64 {
+        self.generic().x.exact()
+    }
+
+    pub fn get_exact_y(&self) -> f64 {
+        self.generic().y.exact()
+    }
+
+    pub fn get_exact_width(&self) -> f64 {
+        self.generic().width.exact()
+    }
+
+    pub fn get_exact_height(&self) -> f64 {
+        self.generic().height.exact()
+    }
+
+    /// Turns float coordinates and sizes into integers according to `mode`
+    pub(crate) fn round_numbers(&mut self, mode: NumberMode) {
+        let generic = self.generic_mut();
+        for number in [&mut generic.x, &mut generic.y] {
+            number.apply(mode);
+        }
+        for number in [&mut generic.width, &mut generic.height] {
+            number.apply(mode);
+        }
+    }
+}
+
 impl From<GroupNode> for Node {
     fn from(node: GroupNode) -> Self {
         Node::Group(node)
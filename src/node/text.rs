@@ -1,4 +1,5 @@
 use ambassador::Delegate;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::NodeId;
@@ -7,12 +8,12 @@ use crate::{color::Color, PixelCoordinate, PixelDimension};
 use super::ambassador_impl_GenericNodeInfo;
 use super::{GenericNode, GenericNodeInfo};
 
-#[derive(Debug, Delegate, Serialize, Deserialize)]
+#[derive(Debug, Clone, Delegate, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[delegate(GenericNodeInfo, target = "generic")]
 pub struct TextNode {
     #[serde(flatten)]
-    generic: GenericNode,
-    text: String,
+    pub(super) generic: GenericNode,
+    pub(super) text: String,
 }
 
 impl TextNode {
@@ -0,0 +1,204 @@
+use std::borrow::Cow;
+use std::fmt::Formatter;
+
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::{Error, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{JsonCanvas, JsonCanvasError, NodeId, PixelCoordinate, PixelDimension};
+
+/// How to handle coordinates and sizes written as floats, like `"x": -120.5`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberMode {
+    /// Round to the nearest integer
+    Round,
+    /// Round down
+    Floor,
+    /// Keep the exact value, so that it is written back unchanged. The integer
+    /// getters return the rounded value.
+    #[default]
+    Exact,
+}
+
+macro_rules! number_type {
+    ($name: ident, $int: ty, $expected: literal, $schema: tt) => {
+        /// Integer which may have been read from a float, keeping its exact value
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub(crate) struct $name {
+            value: $int,
+            exact: Option<f64>,
+        }
+
+        impl $name {
+            pub(crate) fn get(self) -> $int {
+                self.value
+            }
+
+            pub(crate) fn exact(self) -> f64 {
+                self.exact.unwrap_or(self.value as f64)
+            }
+
+            /// Turns a float into an integer, unless `mode` is [`NumberMode::Exact`]
+            pub(crate) fn apply(&mut self, mode: NumberMode) {
+                let Some(exact) = self.exact else {
+                    return;
+                };
+                let value = match mode {
+                    NumberMode::Round => exact.round(),
+                    NumberMode::Floor => exact.floor(),
+                    NumberMode::Exact => return,
+                };
+                *self = (value as $int).into();
+            }
+        }
+
+        impl From<$int> for $name {
+            fn from(value: $int) -> Self {
+                Self { value, exact: None }
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self.exact {
+                    Some(exact) => serializer.serialize_f64(exact),
+                    None => self.value.serialize(serializer),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct NumberVisitor;
+
+                impl Visitor<'_> for NumberVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                        f.write_str($expected)
+                    }
+
+                    fn visit_i64<E: Error>(self, v: i64) -> Result<$name, E> {
+                        <$int>::try_from(v)
+                            .map(Into::into)
+                            .map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+                    }
+
+                    fn visit_u64<E: Error>(self, v: u64) -> Result<$name, E> {
+                        <$int>::try_from(v)
+                            .map(Into::into)
+                            .map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))
+                    }
+
+                    fn visit_f64<E: Error>(self, v: f64) -> Result<$name, E> {
+                        if !(<$int>::MIN as f64..=<$int>::MAX as f64).contains(&v) {
+                            return Err(E::invalid_value(Unexpected::Float(v), &self));
+                        }
+                        Ok($name {
+                            value: v.round() as $int,
+                            exact: Some(v),
+                        })
+                    }
+                }
+
+                deserializer.deserialize_any(NumberVisitor)
+            }
+        }
+
+        impl JsonSchema for $name {
+            fn inline_schema() -> bool {
+                true
+            }
+
+            fn schema_name() -> Cow<'static, str> {
+                stringify!($name).into()
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                json_schema!($schema)
+            }
+        }
+    };
+}
+
+number_type!(Coordinate, PixelCoordinate, "a coordinate", { "type": "number" });
+number_type!(Dimension, PixelDimension, "a non-negative size", {
+    "type": "number",
+    "minimum": 0
+});
+
+impl Coordinate {
+    /// Moves the coordinate by `distance`, keeping the fractional part of a float
+    pub(crate) fn offset(self, distance: PixelCoordinate) -> Self {
+        match self.exact {
+            Some(exact) => {
+                let exact = exact + distance as f64;
+                Self {
+                    value: exact.round() as PixelCoordinate,
+                    exact: Some(exact),
+                }
+            }
+            None => (self.value + distance).into(),
+        }
+    }
+}
+
+impl JsonCanvas {
+    /// Parses a canvas, handling float coordinates and sizes according to `mode`.
+    ///
+    /// Parsing with [`str::parse`] keeps the exact values.
+    pub fn parse_with(s: &str, mode: NumberMode) -> Result<JsonCanvas, JsonCanvasError> {
+        let mut canvas: JsonCanvas = s.parse()?;
+        canvas.round_numbers(mode);
+        Ok(canvas)
+    }
+
+    /// Turns the float coordinates and sizes of every node into integers according
+    /// to `mode`
+    pub fn round_numbers(&mut self, mode: NumberMode) {
+        if mode == NumberMode::Exact {
+            return;
+        }
+        let ids: Vec<NodeId> = self.get_nodes().keys().cloned().collect();
+        for id in &ids {
+            if let Some(mut node) = self.node_mut(id) {
+                node.round_numbers(mode);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::GenericNodeInfo;
+
+    #[test]
+    fn floats_roundtrip() {
+        let mut x: Coordinate = serde_json::from_str("-120.5").unwrap();
+        assert_eq!(x.get(), -121);
+        assert_eq!(serde_json::to_string(&x).unwrap(), "-120.5");
+
+        x.apply(NumberMode::Floor);
+        assert_eq!(serde_json::to_string(&x).unwrap(), "-121");
+        assert!(serde_json::from_str::<Dimension>("-0.5").is_err());
+    }
+
+    #[test]
+    fn canvas_modes() {
+        let source = r#"{"nodes":[{"type":"text","id":"a","x":-120.5,"y":10,"width":100.25,"height":50,"text":"A"}]}"#;
+        let canvas: JsonCanvas = source.parse().unwrap();
+        assert_eq!(canvas.to_string(), source);
+        let node = canvas.nodes().next().unwrap();
+        assert_eq!((node.get_x(), node.get_exact_x()), (-121, -120.5));
+
+        let canvas = JsonCanvas::parse_with(source, NumberMode::Floor).unwrap();
+        let node = canvas.nodes().next().unwrap();
+        assert_eq!((node.get_x(), node.get_width()), (-121, 100));
+        assert_eq!(node.get_exact_width(), 100.0);
+        let canvas = JsonCanvas::parse_with(source, NumberMode::Round).unwrap();
+        assert!(canvas
+            .to_string()
+            .contains(r#""x":-121,"y":10,"width":100,"#));
+    }
+}
@@ -0,0 +1,192 @@
+//! Map remembering the order of its keys.
+//!
+//! The canvas exposes its nodes and edges as `HashMap`s, and keeps their z-order
+//! next to them. When a map is changed directly through
+//! [`OrderedMap::map_mut`], the order is repaired on next use: removed keys are
+//! dropped and new keys are appended, sorted so that the result is deterministic.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+
+#[derive(Clone)]
+pub(crate) struct OrderedMap<K, V> {
+    map: HashMap<K, V>,
+    order: Vec<K>,
+    /// Whether `order` may be out of date, after `map_mut`
+    stale: bool,
+}
+
+impl<K: Hash + Eq + Ord + Clone, V> OrderedMap<K, V> {
+    pub(crate) fn map(&self) -> &HashMap<K, V> {
+        &self.map
+    }
+
+    /// The map, changed without updating the order
+    pub(crate) fn map_mut(&mut self) -> &mut HashMap<K, V> {
+        self.stale = true;
+        &mut self.map
+    }
+
+    /// Entries in order
+    pub(crate) fn into_entries(mut self) -> impl Iterator<Item = (K, V)> {
+        self.sync();
+        let mut map = self.map;
+        self.order.into_iter().map(move |key| {
+            let value = map.remove(&key).expect("order has the keys of the map");
+            (key, value)
+        })
+    }
+
+    /// Keys in order
+    pub(crate) fn keys(&self) -> Vec<&K> {
+        if !self.stale {
+            return self.order.iter().collect();
+        }
+        let mut seen = HashSet::new();
+        let mut keys: Vec<&K> = self
+            .order
+            .iter()
+            .filter(|key| self.map.contains_key(*key) && seen.insert(*key))
+            .collect();
+        let mut new: Vec<&K> = self.map.keys().filter(|key| !seen.contains(key)).collect();
+        new.sort();
+        keys.append(&mut new);
+        keys
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> + Clone {
+        self.keys().into_iter().map(|key| (key, &self.map[key]))
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &V> + Clone {
+        self.iter().map(|(_, value)| value)
+    }
+
+    fn sync(&mut self) {
+        if self.stale {
+            self.order = self.keys().into_iter().cloned().collect();
+            self.stale = false;
+        }
+    }
+
+    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    pub(crate) fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    pub(crate) fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub(crate) fn get_index_of(&self, key: &K) -> Option<usize> {
+        if !self.map.contains_key(key) {
+            return None;
+        }
+        self.keys().into_iter().position(|other| other == key)
+    }
+
+    /// Inserts at the end, or replaces the value in place if the key exists
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.sync();
+        if !self.map.contains_key(&key) {
+            self.order.push(key.clone());
+        }
+        self.map.insert(key, value)
+    }
+
+    /// Inserts a new key at `index`, or at the end if `index` is past it
+    pub(crate) fn insert_at(&mut self, index: usize, key: K, value: V) {
+        self.sync();
+        if self.map.insert(key.clone(), value).is_none() {
+            let index = index.min(self.order.len());
+            self.order.insert(index, key);
+        }
+    }
+
+    /// Removes a key, returning its index and value
+    pub(crate) fn remove(&mut self, key: &K) -> Option<(usize, V)> {
+        self.sync();
+        let value = self.map.remove(key)?;
+        let index = self.order.iter().position(|other| other == key)?;
+        self.order.remove(index);
+        Some((index, value))
+    }
+
+    /// Moves a key to `index`, returning false if it does not exist
+    pub(crate) fn move_to(&mut self, key: &K, index: usize) -> bool {
+        self.sync();
+        let Some(current) = self.order.iter().position(|other| other == key) else {
+            return false;
+        };
+        let key = self.order.remove(current);
+        let index = index.min(self.order.len());
+        self.order.insert(index, key);
+        true
+    }
+}
+
+impl<K, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        Self {
+            map: HashMap::new(),
+            order: Vec::new(),
+            stale: false,
+        }
+    }
+}
+
+/// Keeps the first position of a key inserted twice, with the last value
+impl<K: Hash + Eq + Ord + Clone, V> FromIterator<(K, V)> for OrderedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = OrderedMap::default();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+/// Ignores the order, like comparing the maps returned by the canvas accessors
+impl<K: Hash + Eq, V: PartialEq> PartialEq for OrderedMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: Hash + Eq + Ord + Clone + Debug, V: Debug> Debug for OrderedMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repair_order_after_direct_changes() {
+        let mut map: OrderedMap<&str, i32> = [("c", 1), ("a", 2), ("b", 3)].into_iter().collect();
+        map.map_mut().remove("a");
+        map.map_mut().insert("e", 4);
+        map.map_mut().insert("d", 5);
+        assert_eq!(map.keys(), [&"c", &"b", &"d", &"e"]);
+
+        map.insert_at(0, "a", 6);
+        assert_eq!(map.remove(&"b"), Some((2, 3)));
+        assert!(map.move_to(&"e", 1));
+        assert_eq!(map.keys(), [&"a", &"e", &"c", &"d"]);
+        assert_eq!(map.get_index_of(&"c"), Some(2));
+    }
+}
@@ -0,0 +1,361 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::geometry::Rect;
+use crate::node::{GenericNodeInfo, Node};
+use crate::{JsonCanvas, NodeId, PixelCoordinate, PixelDimension};
+
+const MAX_PASSES: usize = 256;
+
+/// Two nodes whose rectangles intersect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+    pub first: NodeId,
+    pub second: NodeId,
+    pub intersection: Rect,
+}
+
+/// Outcome of [`JsonCanvas::remove_overlaps`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OverlapRemoval {
+    /// IDs of the moved nodes, sorted
+    pub moved: Vec<NodeId>,
+    /// Overlaps left when giving up, as separating two nodes can make new overlaps
+    pub remaining: Vec<Overlap>,
+}
+
+impl OverlapRemoval {
+    pub fn is_complete(&self) -> bool {
+        self.remaining.is_empty()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+impl JsonCanvas {
+    /// Lists every pair of intersecting nodes, sorted by ID.
+    ///
+    /// A group fully containing another node is not reported, as this is how
+    /// nodes are placed inside groups.
+    pub fn overlaps(&self) -> Vec<Overlap> {
+        let nodes: Vec<Entry> = self.entries().into_values().collect();
+
+        let mut overlaps = Vec::new();
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                if let Some(intersection) = overlap(a, b) {
+                    overlaps.push(Overlap {
+                        first: a.id.clone(),
+                        second: b.id.clone(),
+                        intersection,
+                    });
+                }
+            }
+        }
+        overlaps
+    }
+
+    /// Pushes overlapping nodes apart, leaving at least `spacing` pixels between them.
+    ///
+    /// Each overlapping pair is separated along the axis needing the smallest push.
+    /// Along each axis, the nodes are then placed one by one in order, each at the
+    /// smallest distance from its position clearing the nodes placed before it, so
+    /// the relative order of the nodes is kept. Moving a group also moves the nodes
+    /// it contains.
+    ///
+    /// Repeats while moving a node makes new overlaps, giving up after a fixed number
+    /// of passes and reporting the overlaps left.
+    pub fn remove_overlaps(&mut self, spacing: PixelDimension) -> OverlapRemoval {
+        let spacing = spacing as PixelCoordinate;
+        let mut moved = BTreeSet::new();
+
+        for _ in 0..MAX_PASSES {
+            let overlaps = self.overlaps();
+            if overlaps.is_empty() {
+                break;
+            }
+
+            let entries = self.entries();
+            let (mut along_x, mut along_y) = (BTreeSet::new(), BTreeSet::new());
+            for Overlap { first, second, .. } in overlaps {
+                let (a, b) = (&entries[&first], &entries[&second]);
+                if Axis::X.push(a, b, spacing) <= Axis::Y.push(a, b, spacing) {
+                    along_x.insert((first, second));
+                } else {
+                    along_y.insert((first, second));
+                }
+            }
+            moved.extend(self.sweep(Axis::X, &along_x, &along_y, spacing));
+            moved.extend(self.sweep(Axis::Y, &along_y, &along_x, spacing));
+        }
+
+        OverlapRemoval {
+            moved: moved.into_iter().collect(),
+            remaining: self.overlaps(),
+        }
+    }
+
+    fn entries(&self) -> BTreeMap<NodeId, Entry> {
+        self.get_nodes()
+            .values()
+            .map(|node| {
+                let entry = Entry {
+                    id: node.id().clone(),
+                    rect: Rect::of(node),
+                    group: matches!(node, Node::Group(_)),
+                };
+                (entry.id.clone(), entry)
+            })
+            .collect()
+    }
+
+    /// Places every node once along `axis`, moving it past the nodes placed before it
+    /// which it is paired with in `separate` or overlaps, unless they are paired in
+    /// `skip`. Returns the IDs of the moved nodes.
+    fn sweep(
+        &mut self,
+        axis: Axis,
+        separate: &BTreeSet<(NodeId, NodeId)>,
+        skip: &BTreeSet<(NodeId, NodeId)>,
+        spacing: PixelCoordinate,
+    ) -> Vec<NodeId> {
+        let mut entries: Vec<Entry> = self.entries().into_values().collect();
+        entries.sort_by(|a, b| axis.order(a, b));
+        let content: BTreeMap<NodeId, Vec<NodeId>> = entries
+            .iter()
+            .filter(|entry| entry.group)
+            .map(|entry| (entry.id.clone(), self.group_content(&entry.id)))
+            .collect();
+
+        let mut inherited: BTreeMap<NodeId, PixelCoordinate> = BTreeMap::new();
+        let mut placed: Vec<Entry> = Vec::with_capacity(entries.len());
+        let mut moved = Vec::new();
+        for mut entry in entries {
+            let start = axis.start(&entry.rect);
+            let mut position = start + inherited.get(&entry.id).copied().unwrap_or(0);
+            loop {
+                let candidate = Entry {
+                    rect: axis.at(&entry.rect, position),
+                    ..entry.clone()
+                };
+                let next = placed
+                    .iter()
+                    .filter(|other| {
+                        let pair = pair(&other.id, &entry.id);
+                        !skip.contains(&pair)
+                            && (separate.contains(&pair) || overlap(other, &candidate).is_some())
+                    })
+                    .map(|other| axis.end(&other.rect) + spacing)
+                    .max();
+                match next {
+                    Some(next) if next > position => position = next,
+                    _ => break,
+                }
+            }
+
+            let shift = position - start;
+            if shift != 0 {
+                let (dx, dy) = axis.translation(shift);
+                if let Some(mut node) = self.node_mut(&entry.id) {
+                    node.translate(dx, dy);
+                }
+                for id in content.get(&entry.id).into_iter().flatten() {
+                    let distance = inherited.entry(id.clone()).or_default();
+                    *distance = (*distance).max(shift);
+                }
+                moved.push(entry.id.clone());
+            }
+            entry.rect = axis.at(&entry.rect, position);
+            placed.push(entry);
+        }
+        moved
+    }
+}
+
+/// Node as placed while removing overlaps
+#[derive(Clone)]
+struct Entry {
+    id: NodeId,
+    rect: Rect,
+    group: bool,
+}
+
+impl Axis {
+    fn start(self, rect: &Rect) -> PixelCoordinate {
+        match self {
+            Axis::X => rect.x,
+            Axis::Y => rect.y,
+        }
+    }
+
+    fn end(self, rect: &Rect) -> PixelCoordinate {
+        match self {
+            Axis::X => rect.right(),
+            Axis::Y => rect.bottom(),
+        }
+    }
+
+    /// `rect` moved to `position` along the axis
+    fn at(self, rect: &Rect, position: PixelCoordinate) -> Rect {
+        match self {
+            Axis::X => Rect {
+                x: position,
+                ..*rect
+            },
+            Axis::Y => Rect {
+                y: position,
+                ..*rect
+            },
+        }
+    }
+
+    fn translation(self, distance: PixelCoordinate) -> (PixelCoordinate, PixelCoordinate) {
+        match self {
+            Axis::X => (distance, 0),
+            Axis::Y => (0, distance),
+        }
+    }
+
+    /// Order of the nodes along the axis. A group comes before the nodes it contains.
+    fn order(self, a: &Entry, b: &Entry) -> Ordering {
+        let key = |entry: &Entry| {
+            let size = self.end(&entry.rect) - self.start(&entry.rect);
+            (self.start(&entry.rect), Reverse(size), !entry.group)
+        };
+        key(a).cmp(&key(b)).then_with(|| a.id.cmp(&b.id))
+    }
+
+    /// Distance to move the node coming last along the axis to clear the other one
+    fn push(self, a: &Entry, b: &Entry, spacing: PixelCoordinate) -> PixelCoordinate {
+        let (before, after) = match self.order(a, b) {
+            Ordering::Greater => (b, a),
+            _ => (a, b),
+        };
+        self.end(&before.rect) + spacing - self.start(&after.rect)
+    }
+}
+
+fn pair(a: &NodeId, b: &NodeId) -> (NodeId, NodeId) {
+    if a <= b {
+        (a.clone(), b.clone())
+    } else {
+        (b.clone(), a.clone())
+    }
+}
+
+/// Intersection of two nodes, unless one is a group holding the other
+fn overlap(a: &Entry, b: &Entry) -> Option<Rect> {
+    let intersection = a.rect.intersection(&b.rect)?;
+    if a.group && a.rect.contains(&b.rect) {
+        return None;
+    }
+    if b.group && b.rect.contains(&a.rect) {
+        return None;
+    }
+    Some(intersection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{GroupNode, TextNode};
+
+    fn text(id: &str, x: PixelCoordinate, y: PixelCoordinate) -> Node {
+        TextNode::new(id.parse().unwrap(), x, y, 100, 100, None, id.to_string()).into()
+    }
+
+    #[test]
+    fn overlaps_skip_group_containment() {
+        let mut canvas = JsonCanvas::default();
+        canvas
+            .add_node(
+                GroupNode::new("group".parse().unwrap(), 0, 0, 500, 500, None, None, None).into(),
+            )
+            .unwrap();
+        canvas.add_node(text("a", 10, 10)).unwrap();
+        canvas.add_node(text("b", 60, 20)).unwrap();
+        canvas.add_node(text("c", 450, 450)).unwrap();
+
+        let pairs: Vec<(String, String)> = canvas
+            .overlaps()
+            .into_iter()
+            .map(|o| (o.first.into_inner(), o.second.into_inner()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("a".to_string(), "b".to_string()),
+                ("c".to_string(), "group".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_overlaps_keeps_order() {
+        let mut canvas = JsonCanvas::default();
+        canvas.add_node(text("a", 0, 0)).unwrap();
+        canvas.add_node(text("b", 30, 90)).unwrap();
+        canvas.add_node(text("c", 50, 10)).unwrap();
+
+        let removal = canvas.remove_overlaps(10);
+
+        assert!(removal.is_complete());
+        assert!(canvas.overlaps().is_empty());
+        assert!(!removal.moved.contains(&"a".parse().unwrap()));
+        let nodes = canvas.get_nodes();
+        let a = Rect::of(&nodes[&"a".parse::<NodeId>().unwrap()]);
+        let b = Rect::of(&nodes[&"b".parse::<NodeId>().unwrap()]);
+        let c = Rect::of(&nodes[&"c".parse::<NodeId>().unwrap()]);
+        assert_eq!((a.x, a.y), (0, 0));
+        assert!(b.y >= a.bottom() + 10);
+        assert!(c.x >= a.right() + 10);
+    }
+
+    #[test]
+    fn remove_overlaps_separates_row() {
+        // A row in reverse ID order, each node overlapping the next one by a pixel:
+        // separating one pair at a time would push every node into the next one
+        let count = MAX_PASSES + 2;
+        let mut canvas = JsonCanvas::default();
+        for i in 0..count {
+            let x = (count - 1 - i) as PixelCoordinate * 99;
+            canvas.add_node(text(&format!("n{:03}", i), x, 0)).unwrap();
+        }
+
+        let removal = canvas.remove_overlaps(0);
+        assert!(removal.is_complete());
+        assert_eq!(canvas.overlaps().len(), 0);
+        assert_eq!(removal.moved.len(), count - 1);
+        for node in canvas.nodes() {
+            let i: usize = node.id().as_str()[1..].parse().unwrap();
+            assert_eq!(
+                (node.get_x(), node.get_y()),
+                ((count - 1 - i) as PixelCoordinate * 100, 0)
+            );
+        }
+    }
+
+    #[test]
+    fn remove_overlaps_moves_group_content() {
+        let mut canvas = JsonCanvas::default();
+        canvas.add_node(text("a", 0, 0)).unwrap();
+        canvas
+            .add_node(
+                GroupNode::new("group".parse().unwrap(), 50, 20, 300, 300, None, None, None).into(),
+            )
+            .unwrap();
+        canvas.add_node(text("b", 100, 100)).unwrap();
+
+        let removal = canvas.remove_overlaps(0);
+        assert!(removal.is_complete());
+        let nodes = canvas.get_nodes();
+        let group = Rect::of(&nodes[&"group".parse::<NodeId>().unwrap()]);
+        let b = Rect::of(&nodes[&"b".parse::<NodeId>().unwrap()]);
+        assert_eq!((group.x, group.y), (100, 20));
+        assert_eq!((b.x, b.y), (150, 100));
+    }
+}
@@ -0,0 +1,785 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::color::Color;
+use crate::edge::{Edge, End, Side};
+use crate::node::{BackgroundStyle, Node};
+use crate::number::{Coordinate, Dimension};
+use crate::{JsonCanvas, JsonCanvasError};
+
+/// Invalid canvas document, with the location of the problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// JSON path of the invalid value, like `nodes[42].color`, or empty for the whole
+    /// document
+    pub path: String,
+    /// ID of the node or edge holding the invalid value, when known
+    pub id: Option<String>,
+    /// Line of the invalid value, counted from 1
+    pub line: usize,
+    /// Column of the invalid value, counted in bytes from 1 like serde_json does
+    pub column: usize,
+    pub reason: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{} (", self.reason)?;
+        if let Some(id) = &self.id {
+            write!(f, "id {}, ", id)?;
+        }
+        write!(f, "line {}, column {})", self.line, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(err: serde_json::Error) -> Self {
+        let reason = err.to_string();
+        // serde_json appends the location, which is reported separately
+        let reason = match reason.rfind(" at line ") {
+            Some(end) if err.line() > 0 => reason[..end].to_string(),
+            _ => reason,
+        };
+        ParseError {
+            path: String::new(),
+            id: None,
+            line: err.line(),
+            column: err.column(),
+            reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+pub(crate) fn path_string(path: &[Segment]) -> String {
+    let mut s = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if s.is_empty() => s.push_str(key),
+            Segment::Key(key) => {
+                s.push('.');
+                s.push_str(key);
+            }
+            Segment::Index(index) => s.push_str(&format!("[{}]", index)),
+        }
+    }
+    s
+}
+
+/// Parses a canvas, looking for the precise cause of a failure
+pub(crate) fn parse(s: &str) -> Result<JsonCanvas, ParseError> {
+    let err = match serde_json::from_str(s) {
+        Ok(canvas) => return Ok(canvas),
+        Err(err) => err,
+    };
+    // Syntax errors are already precise
+    let value: Value = serde_json::from_str(s)?;
+    let Some(problem) = diagnose(&value) else {
+        return Err(err.into());
+    };
+    let (line, column) = locate(s, &problem.path);
+    Err(ParseError {
+        path: path_string(&problem.path),
+        id: problem.id,
+        line,
+        column,
+        reason: problem.reason,
+    })
+}
+
+struct Problem {
+    path: Vec<Segment>,
+    id: Option<String>,
+    reason: String,
+}
+
+/// What a field holds, checked leniently here and strictly by [`crate::validate`]
+#[derive(Clone, Copy)]
+pub(crate) enum Expect {
+    String,
+    /// String which must start with `#` according to the specification
+    Subpath,
+    Integer,
+    Dimension,
+    Color,
+    Url,
+    Side,
+    End,
+    BackgroundStyle,
+}
+
+/// Fields shared by every node type, with whether they are required
+pub(crate) const NODE_FIELDS: [(&str, bool, Expect); 6] = [
+    ("id", true, Expect::String),
+    ("x", true, Expect::Integer),
+    ("y", true, Expect::Integer),
+    ("width", true, Expect::Dimension),
+    ("height", true, Expect::Dimension),
+    ("color", false, Expect::Color),
+];
+
+pub(crate) const EDGE_FIELDS: [(&str, bool, Expect); 9] = [
+    ("id", true, Expect::String),
+    ("fromNode", true, Expect::String),
+    ("fromSide", false, Expect::Side),
+    ("fromEnd", false, Expect::End),
+    ("toNode", true, Expect::String),
+    ("toSide", false, Expect::Side),
+    ("toEnd", false, Expect::End),
+    ("color", false, Expect::Color),
+    ("label", false, Expect::String),
+];
+
+/// Fields specific to a node type, or `None` for an unknown type
+pub(crate) fn type_fields(node_type: &str) -> Option<&'static [(&'static str, bool, Expect)]> {
+    match node_type {
+        "text" => Some(&[("text", true, Expect::String)]),
+        "file" => Some(&[
+            ("file", true, Expect::String),
+            ("subpath", false, Expect::Subpath),
+        ]),
+        "link" => Some(&[("url", true, Expect::Url)]),
+        "group" => Some(&[
+            ("label", false, Expect::String),
+            ("background", false, Expect::String),
+            ("backgroundStyle", false, Expect::BackgroundStyle),
+        ]),
+        _ => None,
+    }
+}
+
+/// Finds the first invalid value of a canvas which is valid JSON
+fn diagnose(value: &Value) -> Option<Problem> {
+    let Value::Object(root) = value else {
+        return Some(Problem {
+            path: Vec::new(),
+            id: None,
+            reason: format!("expected a canvas object, found {}", value),
+        });
+    };
+    for key in ["nodes", "edges"] {
+        let Some(items) = root.get(key) else {
+            continue;
+        };
+        let mut path = vec![Segment::Key(key.to_string())];
+        let Value::Array(items) = items else {
+            return Some(Problem {
+                path,
+                id: None,
+                reason: format!("expected an array, found {}", items),
+            });
+        };
+        for (index, item) in items.iter().enumerate() {
+            if let Some((field, reason)) = diagnose_item(key, item) {
+                path.push(Segment::Index(index));
+                path.extend(field.map(Segment::Key));
+                return Some(Problem {
+                    path,
+                    id: item.get("id").and_then(Value::as_str).map(str::to_string),
+                    reason,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Checks an item of the `nodes` or `edges` array, returning the invalid field if any,
+/// and the reason
+pub(crate) fn diagnose_item(key: &str, item: &Value) -> Option<(Option<String>, String)> {
+    match item {
+        Value::Object(fields) if key == "nodes" => check_node(fields),
+        Value::Object(fields) => check_fields(fields, &EDGE_FIELDS),
+        _ => Some((None, format!("expected an object, found {}", item))),
+    }
+}
+
+/// Checks a node, returning the invalid field if any, and the reason
+fn check_node(fields: &Map<String, Value>) -> Option<(Option<String>, String)> {
+    let node_type = match fields.get("type") {
+        None => return Some((None, "missing field `type`".to_string())),
+        Some(Value::String(node_type)) => node_type,
+        Some(other) => {
+            return Some((
+                Some("type".to_string()),
+                format!("expected a string, found {}", other),
+            ))
+        }
+    };
+    let Some(specific) = type_fields(node_type) else {
+        return Some((
+            Some("type".to_string()),
+            format!(
+                "unknown node type \"{}\", expected one of text, file, link, group",
+                node_type
+            ),
+        ));
+    };
+    check_fields(fields, &NODE_FIELDS).or_else(|| check_fields(fields, specific))
+}
+
+fn check_fields(
+    fields: &Map<String, Value>,
+    expected: &[(&str, bool, Expect)],
+) -> Option<(Option<String>, String)> {
+    for (name, required, expect) in expected {
+        match fields.get(*name) {
+            None if *required => return Some((None, format!("missing field `{}`", name))),
+            None => {}
+            Some(value) => {
+                if let Err(reason) = check(value, *expect) {
+                    return Some((Some(name.to_string()), reason));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn check(value: &Value, expect: Expect) -> Result<(), String> {
+    let valid = match expect {
+        Expect::String | Expect::Subpath => value.is_string(),
+        Expect::Integer => Coordinate::deserialize(value).is_ok(),
+        Expect::Dimension => Dimension::deserialize(value).is_ok(),
+        Expect::Color => Color::deserialize(value).is_ok(),
+        Expect::Url => match value.as_str().map(Url::parse) {
+            Some(Err(err)) => return Err(format!("invalid URL {}: {}", value, err)),
+            parsed => parsed.is_some(),
+        },
+        Expect::Side => Side::deserialize(value).is_ok(),
+        Expect::End => End::deserialize(value).is_ok(),
+        Expect::BackgroundStyle => BackgroundStyle::deserialize(value).is_ok(),
+    };
+    if valid {
+        return Ok(());
+    }
+    let expected = match expect {
+        Expect::String | Expect::Subpath => "expected a string",
+        Expect::Integer => "expected a number",
+        Expect::Dimension => "expected a non-negative number",
+        Expect::Color => "invalid color, expected a preset from \"1\" to \"6\" or a hex color",
+        Expect::Url => "expected a URL",
+        Expect::Side => "invalid side, expected one of top, right, bottom, left",
+        Expect::End => "invalid end, expected none or arrow",
+        Expect::BackgroundStyle => "invalid background style, expected one of cover, ratio, repeat",
+    };
+    Err(format!("{}, found {}", expected, value))
+}
+
+/// Walks the JSON text along a path, without decoding it
+pub(crate) struct Scanner<'a> {
+    pub(crate) text: &'a [u8],
+    pub(crate) pos: usize,
+}
+
+impl Scanner<'_> {
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    pub(crate) fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    pub(crate) fn expect(&mut self, byte: u8) -> Option<()> {
+        self.skip_whitespace();
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    /// Skips a string, returning its raw content
+    pub(crate) fn string(&mut self) -> Option<&[u8]> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        self.text.get(start..self.pos - 1)
+    }
+
+    pub(crate) fn skip_value(&mut self) -> Option<()> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => {
+                self.string()?;
+            }
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.pos += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Skips a string, returning its decoded content
+    pub(crate) fn key(&mut self) -> Option<String> {
+        match std::str::from_utf8(self.string()?).ok()? {
+            raw if raw.contains('\\') => serde_json::from_str(&format!("\"{}\"", raw)).ok(),
+            raw => Some(raw.to_string()),
+        }
+    }
+
+    /// Moves to the start of the value at `segment` in the current value
+    fn enter(&mut self, segment: &Segment) -> Option<()> {
+        match segment {
+            Segment::Key(key) => {
+                self.expect(b'{')?;
+                loop {
+                    let found = self.key()?;
+                    self.expect(b':')?;
+                    if found == *key {
+                        break;
+                    }
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+            }
+            Segment::Index(index) => {
+                self.expect(b'[')?;
+                for _ in 0..*index {
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+            }
+        }
+        self.skip_whitespace();
+        Some(())
+    }
+}
+
+/// Line and byte column of the value at `path`, or of its closest existing parent
+pub(crate) fn locate(text: &str, path: &[Segment]) -> (usize, usize) {
+    let mut scanner = Scanner {
+        text: text.as_bytes(),
+        pos: 0,
+    };
+    scanner.skip_whitespace();
+    for segment in path {
+        let start = scanner.pos;
+        if scanner.enter(segment).is_none() {
+            scanner.pos = start;
+            break;
+        }
+    }
+    let before = &text[..scanner.pos];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").len() + 1;
+    (line, column)
+}
+
+/// Invalid value repaired or skipped by [`JsonCanvas::parse_lenient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// JSON path of the invalid value, like `nodes[42].color`
+    pub path: String,
+    /// ID of the node or edge holding the invalid value, when known
+    pub id: Option<String>,
+    /// Line of the invalid value, counted from 1
+    pub line: usize,
+    /// Column of the invalid value, counted in bytes from 1
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} (", self.path, self.message)?;
+        if let Some(id) = &self.id {
+            write!(f, "id {}, ", id)?;
+        }
+        write!(f, "line {}, column {})", self.line, self.column)
+    }
+}
+
+/// Repair made to a node or edge, with the field it applies to
+type Repair = (Option<&'static str>, String);
+
+impl JsonCanvas {
+    /// Parses a canvas, repairing or skipping invalid nodes and edges instead of
+    /// failing.
+    ///
+    /// Negative sizes are flipped, invalid colors, sides and ends are removed, and
+    /// link nodes with an invalid URL become text nodes holding the URL. Other
+    /// invalid nodes are skipped, along with the edges whose nodes are missing. Each
+    /// repair and skip is reported as a warning.
+    ///
+    /// Only a document which is not a JSON object fails to parse.
+    pub fn parse_lenient(s: &str) -> Result<(JsonCanvas, Vec<ParseWarning>), JsonCanvasError> {
+        let value: Value = serde_json::from_str(s)?;
+        let Value::Object(mut root) = value else {
+            return Err(ParseError {
+                path: String::new(),
+                id: None,
+                line: 1,
+                column: 1,
+                reason: "expected a canvas object".to_string(),
+            }
+            .into());
+        };
+
+        let mut canvas = JsonCanvas::default();
+        let mut warnings = Vec::new();
+        let mut warn = |path: Vec<Segment>, id: Option<&str>, message: String| {
+            let (line, column) = locate(s, &path);
+            warnings.push(ParseWarning {
+                path: path_string(&path),
+                id: id.map(str::to_string),
+                line,
+                column,
+                message,
+            });
+        };
+
+        for key in ["nodes", "edges"] {
+            let items = match root.remove(key) {
+                None => continue,
+                Some(Value::Array(items)) => items,
+                Some(other) => {
+                    let path = vec![Segment::Key(key.to_string())];
+                    warn(path, None, format!("ignored {}, expected an array", other));
+                    continue;
+                }
+            };
+            for (index, item) in items.into_iter().enumerate() {
+                let path = vec![Segment::Key(key.to_string()), Segment::Index(index)];
+                let Value::Object(mut fields) = item else {
+                    warn(path, None, format!("skipped {}, expected an object", item));
+                    continue;
+                };
+                let id = fields.get("id").and_then(Value::as_str).map(str::to_string);
+                let mut repairs = Vec::new();
+                let added = if key == "nodes" {
+                    repair_node(&mut fields, &mut repairs)
+                        .and_then(|()| add_lenient(&mut canvas, fields, true))
+                } else {
+                    repair_edge(&mut fields, &mut repairs);
+                    add_lenient(&mut canvas, fields, false)
+                };
+                for (field, message) in repairs {
+                    let mut path = path.clone();
+                    path.extend(field.map(|field| Segment::Key(field.to_string())));
+                    warn(path, id.as_deref(), message);
+                }
+                if let Err((field, reason)) = added {
+                    let mut path = path;
+                    path.extend(field.map(Segment::Key));
+                    let kind = if key == "nodes" { "node" } else { "edge" };
+                    warn(path, id.as_deref(), format!("skipped {}: {}", kind, reason));
+                }
+            }
+        }
+        Ok((canvas, warnings))
+    }
+}
+
+/// Repairs a node in place, returning the field and the reason if it cannot be
+/// repaired
+fn repair_node(
+    fields: &mut Map<String, Value>,
+    repairs: &mut Vec<Repair>,
+) -> Result<(), (Option<String>, String)> {
+    for (size, position) in [("width", "x"), ("height", "y")] {
+        let Some(value) = fields.get(size).and_then(Value::as_f64) else {
+            continue;
+        };
+        if value < 0.0 {
+            let negative = fields[size].clone();
+            let flipped = negate(&negative).ok_or_else(|| {
+                let reason = format!("{} {} cannot be flipped", size, negative);
+                (Some(size.to_string()), reason)
+            })?;
+            let start = match fields.get(position) {
+                Some(start) => Some(add(start, &negative).ok_or_else(|| {
+                    let reason = format!("{} {} cannot be moved by {}", position, start, negative);
+                    (Some(position.to_string()), reason)
+                })?),
+                None => None,
+            };
+            fields.insert(size.to_string(), flipped);
+            if let Some(start) = start {
+                fields.insert(position.to_string(), start);
+            }
+            repairs.push((
+                Some(size),
+                format!("flipped negative {} {}", size, negative),
+            ));
+        }
+    }
+    remove_invalid(fields, "color", Expect::Color, repairs);
+    remove_invalid(fields, "backgroundStyle", Expect::BackgroundStyle, repairs);
+
+    let invalid_url = match (fields.get("type"), fields.get("url")) {
+        (Some(Value::String(node_type)), Some(url)) if node_type == "link" => {
+            check(url, Expect::Url).err().filter(|_| url.is_string())
+        }
+        _ => None,
+    };
+    if let Some(reason) = invalid_url {
+        let url = fields.remove("url").unwrap();
+        fields.insert("type".to_string(), "text".into());
+        fields.insert("text".to_string(), url);
+        repairs.push((Some("url"), format!("turned into a text node: {}", reason)));
+    }
+    Ok(())
+}
+
+/// `-value`, or `None` if it overflows
+fn negate(value: &Value) -> Option<Value> {
+    match value.as_i64() {
+        Some(value) => value.checked_neg().map(Value::from),
+        None => Some((-value.as_f64().unwrap_or_default()).into()),
+    }
+}
+
+/// `a + b`, or `None` if it overflows
+fn add(a: &Value, b: &Value) -> Option<Value> {
+    match (a.as_i64(), b.as_i64()) {
+        (Some(a), Some(b)) => a.checked_add(b).map(Value::from),
+        _ => {
+            let sum = a.as_f64().unwrap_or_default() + b.as_f64().unwrap_or_default();
+            sum.is_finite().then(|| sum.into())
+        }
+    }
+}
+
+fn repair_edge(fields: &mut Map<String, Value>, repairs: &mut Vec<Repair>) {
+    remove_invalid(fields, "color", Expect::Color, repairs);
+    for side in ["fromSide", "toSide"] {
+        remove_invalid(fields, side, Expect::Side, repairs);
+    }
+    for end in ["fromEnd", "toEnd"] {
+        remove_invalid(fields, end, Expect::End, repairs);
+    }
+}
+
+fn remove_invalid(
+    fields: &mut Map<String, Value>,
+    name: &'static str,
+    expect: Expect,
+    repairs: &mut Vec<Repair>,
+) {
+    let Some(value) = fields.get(name) else {
+        return;
+    };
+    if let Err(reason) = check(value, expect) {
+        fields.remove(name);
+        repairs.push((Some(name), format!("removed: {}", reason)));
+    }
+}
+
+/// Adds a repaired node or edge, returning the invalid field and the reason if it
+/// cannot be added
+fn add_lenient(
+    canvas: &mut JsonCanvas,
+    fields: Map<String, Value>,
+    is_node: bool,
+) -> Result<(), (Option<String>, String)> {
+    if is_node {
+        if let Some(problem) = check_node(&fields) {
+            return Err(problem);
+        }
+        let node =
+            Node::deserialize(&Value::Object(fields)).map_err(|err| (None, err.to_string()))?;
+        canvas
+            .add_node(node)
+            .map_err(|err| (Some("id".to_string()), err.to_string()))
+    } else {
+        if let Some(problem) = check_fields(&fields, &EDGE_FIELDS) {
+            return Err(problem);
+        }
+        let edge =
+            Edge::deserialize(&Value::Object(fields)).map_err(|err| (None, err.to_string()))?;
+        canvas.add_edge(edge).map_err(|err| match err {
+            JsonCanvasError::EdgeExists(_) => (Some("id".to_string()), err.to_string()),
+            _ => (None, err.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+    use crate::node::GenericNodeInfo;
+
+    const CANVAS: &str = r##"{
+  "nodes": [
+    {"id": "a", "type": "text", "x": 0, "y": 0, "width": 10, "height": 10, "text": "A"},
+    {"id": "b", "type": "text", "x": 0, "y": 0, "width": 10, "height": 10, "text": "B",
+     "color": "#zz0000"}
+  ],
+  "edges": [
+    {"id": "e", "fromNode": "a", "toNode": "b", "toSide": "up"}
+  ]
+}"##;
+
+    #[test]
+    fn invalid_value() {
+        let err = parse(CANVAS).unwrap_err();
+        assert_eq!(err.path, "nodes[1].color");
+        assert_eq!(err.id.as_deref(), Some("b"));
+        assert_eq!((err.line, err.column), (5, 15));
+        assert!(err.reason.starts_with("invalid color"));
+
+        let fixed = CANVAS.replace("#zz0000", "#ff0000");
+        let err = parse(&fixed).unwrap_err();
+        assert_eq!(err.path, "edges[0].toSide");
+        assert_eq!((err.line, err.column), (8, 59));
+    }
+
+    #[test]
+    fn unknown_type_and_missing_field() {
+        let err = parse(&CANVAS.replace(
+            "\"type\": \"text\", \"x\": 0",
+            "\"type\": \"note\", \"x\": 0",
+        ))
+        .unwrap_err();
+        assert_eq!(err.path, "nodes[0].type");
+        assert_eq!(
+            err.to_string(),
+            "nodes[0].type: unknown node type \"note\", expected one of text, file, link, \
+             group (id a, line 3, column 25)"
+        );
+
+        let fixed = CANVAS.replace("#zz0000", "#ff0000");
+        let err = parse(&fixed.replace("\"toNode\": \"b\", ", "")).unwrap_err();
+        assert_eq!(err.path, "edges[0]");
+        assert_eq!(err.reason, "missing field `toNode`");
+        assert_eq!((err.line, err.column), (8, 5));
+    }
+
+    #[test]
+    fn syntax_error() {
+        let err = parse("{\"nodes\": [\n  {]\n}").unwrap_err();
+        assert_eq!(err.path, "");
+        assert_eq!((err.line, err.column), (2, 4));
+        assert_eq!(err.reason, "key must be a string");
+    }
+
+    #[test]
+    fn columns_count_bytes() {
+        // Both columns are 1 + the byte offset in the line, "é" taking 2 bytes
+        let source = r#"{"nodes": [{"id": "é", "type": "text", "x": 0, "y": 0, "width": 10, "height": 10, "text": "ü", "color": 7}]}"#;
+        let err = parse(source).unwrap_err();
+        assert_eq!(err.path, "nodes[0].color");
+        assert_eq!(err.column, source.find("7}").unwrap() + 1);
+
+        let source = "{\"nodes\": [{\"id\": \"é\" ]}";
+        let err = parse(source).unwrap_err();
+        assert_eq!(err.path, "");
+        assert_eq!(err.column, source.find(']').unwrap() + 1);
+    }
+
+    #[test]
+    fn lenient() {
+        let source = r##"{
+  "nodes": [
+    {"id": "a", "type": "text", "x": 10.6, "y": 0, "width": -100, "height": 50, "text": "A"},
+    {"id": "b", "type": "sticker", "x": 0, "y": 0, "width": 10, "height": 10},
+    {"id": "c", "type": "link", "x": 0, "y": 0, "width": 10, "height": 10, "url": "not a url",
+     "color": "red"}
+  ],
+  "edges": [
+    {"id": "ab", "fromNode": "a", "toNode": "b"},
+    {"id": "ac", "fromNode": "a", "toNode": "c", "toSide": "up"}
+  ]
+}"##;
+        assert!(parse(source).is_err());
+
+        let (canvas, warnings) = JsonCanvas::parse_lenient(source).unwrap();
+        let ids: Vec<&str> = canvas.nodes().map(|node| node.id().as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert_eq!(canvas.get_edges().len(), 1);
+        let a = canvas.nodes().next().unwrap();
+        assert_eq!(Rect::of(a), Rect::new(-89, 0, 100, 50));
+        assert_eq!(a.get_exact_x(), -89.4);
+        assert!(
+            matches!(canvas.nodes().nth(1).unwrap(), Node::Text(text) if text.text() == "not a url")
+        );
+
+        let warnings: Vec<(&str, &str)> = warnings
+            .iter()
+            .map(|warning| (warning.path.as_str(), warning.message.as_str()))
+            .collect();
+        assert_eq!(warnings.len(), 6);
+        assert_eq!(
+            warnings[0],
+            ("nodes[0].width", "flipped negative width -100")
+        );
+        assert!(warnings[1].1.starts_with("skipped node: unknown node type"));
+        assert_eq!(warnings[2].0, "nodes[2].color");
+        assert_eq!(warnings[3].0, "nodes[2].url");
+        assert_eq!(
+            warnings[4],
+            ("edges[0]", "skipped edge: Node b does not exist")
+        );
+        assert_eq!(warnings[5].0, "edges[1].toSide");
+    }
+
+    #[test]
+    fn lenient_overflow_skips() {
+        let min = i64::MIN;
+        for (node, path, message) in [
+            (
+                format!(r#""x": 0, "y": 0, "width": {}, "height": 10"#, min),
+                "nodes[0].width",
+                format!("skipped node: width {} cannot be flipped", min),
+            ),
+            (
+                format!(r#""x": {}, "y": 0, "width": -10, "height": 10"#, min),
+                "nodes[0].x",
+                format!("skipped node: x {} cannot be moved by -10", min),
+            ),
+        ] {
+            let source = format!(
+                r#"{{"nodes": [{{"id": "a", "type": "text", {}, "text": "A"}}]}}"#,
+                node
+            );
+            let (canvas, warnings) = JsonCanvas::parse_lenient(&source).unwrap();
+            assert!(canvas.get_nodes().is_empty());
+            let last = warnings.last().unwrap();
+            assert_eq!((last.path.as_str(), &last.message), (path, &message));
+        }
+    }
+}
@@ -0,0 +1,543 @@
+use std::hash::Hash;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::diff::{diff, Change};
+use crate::edge::Edge;
+use crate::events::CanvasEvent;
+use crate::node::{GenericNodeInfo, Node};
+use crate::ordered::OrderedMap;
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId};
+
+/// Reason why a patch operation cannot be applied to a canvas
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PatchConflict {
+    #[error("node {0} already exists")]
+    NodeExists(NodeId),
+    #[error("node {0} does not exist")]
+    NodeMissing(NodeId),
+    #[error("node {0} does not have the expected value")]
+    NodeMismatch(NodeId),
+    #[error("node {0} is still connected to edges")]
+    NodeConnected(NodeId),
+    #[error("edge {0} already exists")]
+    EdgeExists(EdgeId),
+    #[error("edge {0} does not exist")]
+    EdgeMissing(EdgeId),
+    #[error("edge {0} does not have the expected value")]
+    EdgeMismatch(EdgeId),
+    #[error("edge {0} is connected to a missing node")]
+    EdgeDangling(EdgeId),
+    #[error("the order does not have the expected value")]
+    OrderMismatch,
+}
+
+/// A single operation of a [`CanvasPatch`].
+///
+/// Removals and updates hold the expected current value, which is used to detect
+/// conflicts and to invert the operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum PatchOp {
+    AddNode {
+        node: Node,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<usize>,
+    },
+    RemoveNode {
+        node: Node,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<usize>,
+    },
+    UpdateNode {
+        from: Node,
+        to: Node,
+    },
+    AddEdge {
+        edge: Edge,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<usize>,
+    },
+    RemoveEdge {
+        edge: Edge,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<usize>,
+    },
+    UpdateEdge {
+        from: Edge,
+        to: Edge,
+    },
+    ReorderNodes {
+        from: Vec<NodeId>,
+        to: Vec<NodeId>,
+    },
+    ReorderEdges {
+        from: Vec<EdgeId>,
+        to: Vec<EdgeId>,
+    },
+}
+
+impl PatchOp {
+    pub fn invert(&self) -> PatchOp {
+        match self.clone() {
+            PatchOp::AddNode { node, index } => PatchOp::RemoveNode { node, index },
+            PatchOp::RemoveNode { node, index } => PatchOp::AddNode { node, index },
+            PatchOp::UpdateNode { from, to } => PatchOp::UpdateNode { from: to, to: from },
+            PatchOp::AddEdge { edge, index } => PatchOp::RemoveEdge { edge, index },
+            PatchOp::RemoveEdge { edge, index } => PatchOp::AddEdge { edge, index },
+            PatchOp::UpdateEdge { from, to } => PatchOp::UpdateEdge { from: to, to: from },
+            PatchOp::ReorderNodes { from, to } => PatchOp::ReorderNodes { from: to, to: from },
+            PatchOp::ReorderEdges { from, to } => PatchOp::ReorderEdges { from: to, to: from },
+        }
+    }
+}
+
+/// Serializable list of operations turning one canvas into another
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct CanvasPatch {
+    ops: Vec<PatchOp>,
+}
+
+impl CanvasPatch {
+    pub fn new(ops: Vec<PatchOp>) -> Self {
+        Self { ops }
+    }
+
+    /// Builds the patch turning canvas `a` into canvas `b`, including the order of
+    /// nodes and edges
+    pub fn between(a: &JsonCanvas, b: &JsonCanvas) -> Self {
+        let mut removed_nodes = Vec::new();
+        let mut removed_edges = Vec::new();
+        let mut node_ops = Vec::new();
+        let mut edge_ops = Vec::new();
+
+        for change in diff(a, b) {
+            match change {
+                Change::NodeAdded { id } => node_ops.push(PatchOp::AddNode {
+                    node: b.get_nodes()[&id].clone(),
+                    index: None,
+                }),
+                Change::NodeRemoved { id } => removed_nodes.push(id),
+                Change::NodeMoved { id, .. }
+                | Change::NodeResized { id, .. }
+                | Change::NodeRecolored { id, .. }
+                | Change::NodeTextChanged { id, .. }
+                | Change::NodeContentChanged { id } => {
+                    let op = PatchOp::UpdateNode {
+                        from: a.get_nodes()[&id].clone(),
+                        to: b.get_nodes()[&id].clone(),
+                    };
+                    if node_ops.last() != Some(&op) {
+                        node_ops.push(op);
+                    }
+                }
+                Change::EdgeAdded { id } => edge_ops.push(PatchOp::AddEdge {
+                    edge: b.get_edges()[&id].clone(),
+                    index: None,
+                }),
+                Change::EdgeRemoved { id } => removed_edges.push(id),
+                Change::EdgeEndpointsChanged { id, .. }
+                | Change::EdgeRecolored { id, .. }
+                | Change::EdgeLabelChanged { id, .. } => {
+                    let op = PatchOp::UpdateEdge {
+                        from: a.get_edges()[&id].clone(),
+                        to: b.get_edges()[&id].clone(),
+                    };
+                    if edge_ops.last() != Some(&op) {
+                        edge_ops.push(op);
+                    }
+                }
+            }
+        }
+        // Updated edges may point to added nodes, so they go after node additions
+        edge_ops.sort_by_key(|op| matches!(op, PatchOp::AddEdge { .. }));
+
+        // Removing from the last to the first index keeps each recorded index equal to
+        // the one in `a`, so that the inverted patch puts the items back in place.
+        // Nodes are removed last, once no edge points to them anymore.
+        let (a_nodes, a_edges) = (a.ordered_nodes(), a.ordered_edges());
+        let (b_nodes, b_edges) = (b.ordered_nodes(), b.ordered_edges());
+        let mut ops: Vec<PatchOp> = descending_indices(a_edges, &removed_edges)
+            .map(|(index, edge)| PatchOp::RemoveEdge {
+                edge: edge.clone(),
+                index: Some(index),
+            })
+            .collect();
+        ops.append(&mut node_ops);
+        ops.append(&mut edge_ops);
+        ops.extend(
+            descending_indices(a_nodes, &removed_nodes).map(|(index, node)| PatchOp::RemoveNode {
+                node: node.clone(),
+                index: Some(index),
+            }),
+        );
+
+        // Added items are appended, so restore the order of `b` if it differs
+        let (from, to) = (appended_order(a_nodes, b_nodes), b_nodes.keys());
+        if !from.iter().eq(to.iter().copied()) {
+            ops.push(PatchOp::ReorderNodes {
+                from,
+                to: to.into_iter().cloned().collect(),
+            });
+        }
+        let (from, to) = (appended_order(a_edges, b_edges), b_edges.keys());
+        if !from.iter().eq(to.iter().copied()) {
+            ops.push(PatchOp::ReorderEdges {
+                from,
+                to: to.into_iter().cloned().collect(),
+            });
+        }
+
+        Self { ops }
+    }
+
+    pub fn ops(&self) -> &[PatchOp] {
+        &self.ops
+    }
+
+    pub fn push(&mut self, op: PatchOp) {
+        self.ops.push(op);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns the patch undoing this one
+    pub fn invert(&self) -> CanvasPatch {
+        CanvasPatch {
+            ops: self.ops.iter().rev().map(PatchOp::invert).collect(),
+        }
+    }
+}
+
+impl JsonCanvas {
+    /// Applies every operation of the patch, or none of them.
+    ///
+    /// Fails with [`JsonCanvasError::PatchConflict`] if the canvas does not match
+    /// what an operation expects, in which case the canvas is left untouched.
+    pub fn apply(&mut self, patch: &CanvasPatch) -> Result<(), JsonCanvasError> {
+        self.atomically(|canvas| {
+            let mut done = Vec::with_capacity(patch.ops.len());
+            for (index, op) in patch.ops.iter().enumerate() {
+                match canvas.apply_op(op) {
+                    Ok(op) => done.push(op),
+                    Err(conflict) => {
+                        for op in done.iter().rev() {
+                            canvas
+                                .apply_op(&op.invert())
+                                .expect("inverse of an applied operation must apply");
+                        }
+                        return Err(JsonCanvasError::PatchConflict { index, conflict });
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Applies an operation, returning it with the indices where items were
+    /// actually added or removed, so that its inverse puts them back in place
+    pub(crate) fn apply_op(&mut self, op: &PatchOp) -> Result<PatchOp, PatchConflict> {
+        match op {
+            PatchOp::AddNode { node, index } => {
+                let nodes = self.unobserved_nodes_mut();
+                if nodes.contains_key(node.id()) {
+                    return Err(PatchConflict::NodeExists(node.id().clone()));
+                }
+                let index = index.unwrap_or(nodes.len()).min(nodes.len());
+                nodes.insert_at(index, node.id().clone(), node.clone());
+                self.emit(|| CanvasEvent::NodeAdded(node.clone()));
+                return Ok(PatchOp::AddNode {
+                    node: node.clone(),
+                    index: Some(index),
+                });
+            }
+            PatchOp::RemoveNode { node, .. } => {
+                let id = node.id();
+                match self.get_nodes().get(id) {
+                    None => return Err(PatchConflict::NodeMissing(id.clone())),
+                    Some(current) if current != node => {
+                        return Err(PatchConflict::NodeMismatch(id.clone()))
+                    }
+                    _ => {}
+                }
+                if self
+                    .get_edges()
+                    .values()
+                    .any(|edge| edge.from_node() == id || edge.to_node() == id)
+                {
+                    return Err(PatchConflict::NodeConnected(id.clone()));
+                }
+                let (index, _) = self.unobserved_nodes_mut().remove(id).unwrap();
+                self.emit(|| CanvasEvent::NodeRemoved(node.clone()));
+                return Ok(PatchOp::RemoveNode {
+                    node: node.clone(),
+                    index: Some(index),
+                });
+            }
+            PatchOp::UpdateNode { from, to } => {
+                let id = from.id();
+                if to.id() != id {
+                    return Err(PatchConflict::NodeMismatch(to.id().clone()));
+                }
+                match self.unobserved_nodes_mut().get_mut(id) {
+                    None => return Err(PatchConflict::NodeMissing(id.clone())),
+                    Some(current) if current != from => {
+                        return Err(PatchConflict::NodeMismatch(id.clone()))
+                    }
+                    Some(current) => *current = to.clone(),
+                }
+                if from != to {
+                    self.emit(|| CanvasEvent::NodeUpdated {
+                        old: from.clone(),
+                        new: to.clone(),
+                    });
+                }
+            }
+            PatchOp::AddEdge { edge, index } => {
+                if self.get_edges().contains_key(edge.id()) {
+                    return Err(PatchConflict::EdgeExists(edge.id().clone()));
+                }
+                if !self.get_nodes().contains_key(edge.from_node())
+                    || !self.get_nodes().contains_key(edge.to_node())
+                {
+                    return Err(PatchConflict::EdgeDangling(edge.id().clone()));
+                }
+                let edges = self.unobserved_edges_mut();
+                let index = index.unwrap_or(edges.len()).min(edges.len());
+                edges.insert_at(index, edge.id().clone(), edge.clone());
+                self.emit(|| CanvasEvent::EdgeAdded(edge.clone()));
+                return Ok(PatchOp::AddEdge {
+                    edge: edge.clone(),
+                    index: Some(index),
+                });
+            }
+            PatchOp::RemoveEdge { edge, .. } => {
+                let id = edge.id();
+                match self.get_edges().get(id) {
+                    None => return Err(PatchConflict::EdgeMissing(id.clone())),
+                    Some(current) if current != edge => {
+                        return Err(PatchConflict::EdgeMismatch(id.clone()))
+                    }
+                    _ => {}
+                }
+                let (index, _) = self.unobserved_edges_mut().remove(id).unwrap();
+                self.emit(|| CanvasEvent::EdgeRemoved(edge.clone()));
+                return Ok(PatchOp::RemoveEdge {
+                    edge: edge.clone(),
+                    index: Some(index),
+                });
+            }
+            PatchOp::UpdateEdge { from, to } => {
+                let id = from.id();
+                if to.id() != id {
+                    return Err(PatchConflict::EdgeMismatch(to.id().clone()));
+                }
+                if !self.get_nodes().contains_key(to.from_node())
+                    || !self.get_nodes().contains_key(to.to_node())
+                {
+                    return Err(PatchConflict::EdgeDangling(id.clone()));
+                }
+                match self.unobserved_edges_mut().get_mut(id) {
+                    None => return Err(PatchConflict::EdgeMissing(id.clone())),
+                    Some(current) if current != from => {
+                        return Err(PatchConflict::EdgeMismatch(id.clone()))
+                    }
+                    Some(current) => *current = to.clone(),
+                }
+                if from != to {
+                    self.emit_edge_update(from, to);
+                }
+            }
+            PatchOp::ReorderNodes { from, to } => {
+                let nodes = self.unobserved_nodes_mut();
+                if !nodes.keys().into_iter().eq(from.iter()) || !same_items(from, to) {
+                    return Err(PatchConflict::OrderMismatch);
+                }
+                for (index, id) in to.iter().enumerate() {
+                    nodes.move_to(id, index);
+                }
+                if from != to {
+                    self.emit(|| CanvasEvent::NodesReordered);
+                }
+            }
+            PatchOp::ReorderEdges { from, to } => {
+                let edges = self.unobserved_edges_mut();
+                if !edges.keys().into_iter().eq(from.iter()) || !same_items(from, to) {
+                    return Err(PatchConflict::OrderMismatch);
+                }
+                for (index, id) in to.iter().enumerate() {
+                    edges.move_to(id, index);
+                }
+                if from != to {
+                    self.emit(|| CanvasEvent::EdgesReordered);
+                }
+            }
+        }
+        Ok(op.clone())
+    }
+}
+
+/// Items of `map` with the given keys and their index, from the last to the first
+fn descending_indices<'a, K: Hash + Eq + Ord + Clone, V>(
+    map: &'a OrderedMap<K, V>,
+    keys: &[K],
+) -> impl Iterator<Item = (usize, &'a V)> {
+    let keys: std::collections::HashSet<&K> = keys.iter().collect();
+    let mut items: Vec<(usize, &V)> = map
+        .iter()
+        .enumerate()
+        .filter(|(_, (key, _))| keys.contains(key))
+        .map(|(index, (_, value))| (index, value))
+        .collect();
+    items.reverse();
+    items.into_iter()
+}
+
+/// Order of the items of `b` once the ones missing from `a` are appended to it
+fn appended_order<K: Hash + Eq + Ord + Clone, V>(
+    a: &OrderedMap<K, V>,
+    b: &OrderedMap<K, V>,
+) -> Vec<K> {
+    a.keys()
+        .into_iter()
+        .filter(|key| b.contains_key(key))
+        .chain(b.keys().into_iter().filter(|key| !a.contains_key(key)))
+        .cloned()
+        .collect()
+}
+
+fn same_items<T: Ord + Clone>(a: &[T], b: &[T]) -> bool {
+    let (mut a, mut b) = (a.to_vec(), b.to_vec());
+    a.sort();
+    b.sort();
+    a == b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::PresetColor;
+    use crate::node::TextNode;
+
+    fn text(id: &str, x: i64) -> Node {
+        TextNode::new(id.parse().unwrap(), x, 0, 100, 100, None, id.to_string()).into()
+    }
+
+    fn edge(id: &str, from: &str, to: &str) -> Edge {
+        Edge::new(
+            id.parse().unwrap(),
+            from.parse().unwrap(),
+            None,
+            None,
+            to.parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn canvases() -> (JsonCanvas, JsonCanvas) {
+        let mut a = JsonCanvas::default();
+        a.add_node(text("a", 0)).unwrap();
+        a.add_node(text("b", 0)).unwrap();
+        a.add_node(text("c", 0)).unwrap();
+        a.add_edge(edge("ab", "a", "b")).unwrap();
+        a.add_edge(edge("bc", "b", "c")).unwrap();
+
+        let mut b = JsonCanvas::default();
+        b.add_node(text("d", 0)).unwrap();
+        b.add_node(text("c", 50)).unwrap();
+        b.add_node(text("a", 0)).unwrap();
+        b.add_edge(edge("ca", "c", "a")).unwrap();
+        let mut ad = edge("ab", "a", "d");
+        ad.set_color(PresetColor::Red.into());
+        b.add_edge(ad).unwrap();
+
+        (a, b)
+    }
+
+    fn order(canvas: &JsonCanvas) -> (Vec<&str>, Vec<&str>) {
+        (
+            canvas.nodes().map(|node| node.id().as_str()).collect(),
+            canvas.edges().map(|edge| edge.id().as_str()).collect(),
+        )
+    }
+
+    #[test]
+    fn apply_and_invert() {
+        let (mut a, b) = canvases();
+        let original = a.clone();
+        let patch = CanvasPatch::between(&a, &b);
+
+        a.apply(&patch).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(order(&a), order(&b));
+
+        a.apply(&patch.invert()).unwrap();
+        assert_eq!(a, original);
+        assert_eq!(order(&a), order(&original));
+    }
+
+    #[test]
+    fn conflict_leaves_canvas_untouched() {
+        let (mut a, b) = canvases();
+        let patch = CanvasPatch::between(&a, &b);
+        *a.node_mut(&"c".parse().unwrap()).unwrap() = text("c", 10);
+        let before = a.clone();
+
+        let err = a.apply(&patch).unwrap_err();
+        assert!(matches!(
+            err,
+            JsonCanvasError::PatchConflict {
+                conflict: PatchConflict::NodeMismatch(_),
+                ..
+            }
+        ));
+        assert_eq!(a, before);
+        assert_eq!(order(&a), order(&before));
+    }
+
+    #[test]
+    fn rollback_restores_order() {
+        let (mut a, _) = canvases();
+        let before = a.clone();
+        let patch = CanvasPatch::new(vec![
+            PatchOp::RemoveEdge {
+                edge: edge("ab", "a", "b"),
+                index: None,
+            },
+            PatchOp::RemoveNode {
+                node: text("a", 0),
+                index: None,
+            },
+            PatchOp::UpdateNode {
+                from: text("c", 99),
+                to: text("c", 0),
+            },
+        ]);
+
+        let err = a.apply(&patch).unwrap_err();
+        assert!(matches!(
+            err,
+            JsonCanvasError::PatchConflict { index: 2, .. }
+        ));
+        assert_eq!(a, before);
+        assert_eq!(order(&a), order(&before));
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let (a, b) = canvases();
+        let patch = CanvasPatch::between(&a, &b);
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(serde_json::from_str::<CanvasPatch>(&json).unwrap(), patch);
+    }
+}
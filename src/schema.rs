@@ -0,0 +1,610 @@
+//! JSON Schema of the canvas format, generated from the types of this crate.
+//!
+//! The schema describes what parsing accepts, which is more lenient than the
+//! specification: floats are allowed for coordinates and sizes, and hex colors may
+//! have 3, 4 or 8 digits. Use [`crate::validate`] to check a document against the
+//! specification itself.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::parse::{path_string, Segment};
+use crate::JsonCanvas;
+
+/// Value of a document which does not match a schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaError {
+    /// JSON path of the invalid value, like `nodes[42].color`, or empty for the whole
+    /// document
+    pub path: String,
+    /// Location of the failing keyword in the schema, like `#/$defs/Edge/required`
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Schema using a keyword [`validate_against`] does not support, or an invalid
+/// `pattern`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{schema_path}: {message}")]
+pub struct InvalidSchema {
+    /// Location of the keyword in the schema, like `#/$defs/Edge/uniqueItems`
+    pub schema_path: String,
+    pub message: String,
+}
+
+/// Keywords checked by [`validate_against`]
+const SUPPORTED: [&str; 20] = [
+    "$ref",
+    "type",
+    "enum",
+    "const",
+    "pattern",
+    "format",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "required",
+    "properties",
+    "additionalProperties",
+    "items",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "not",
+];
+
+/// Keywords which never make a value invalid
+const ANNOTATIONS: [&str; 11] = [
+    "$schema",
+    "$id",
+    "$comment",
+    "$defs",
+    "definitions",
+    "title",
+    "description",
+    "default",
+    "examples",
+    "deprecated",
+    "readOnly",
+];
+
+/// JSON Schema (draft 2020-12) of a canvas document. Nodes, edges and colors are
+/// described in `$defs`.
+pub fn canvas_schema() -> Value {
+    schemars::schema_for!(JsonCanvas).to_value()
+}
+
+/// Checks a JSON document against [`canvas_schema`]
+///
+/// ```
+/// use jsoncanvas::schema::validate_schema;
+///
+/// let value = serde_json::json!({"edges": [{"id": "e", "fromNode": "a"}]});
+/// let errors = validate_schema(&value);
+/// assert_eq!(errors[0].to_string(), "edges[0]: missing field `toNode`");
+/// ```
+pub fn validate_schema(value: &Value) -> Vec<SchemaError> {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    validate_against(SCHEMA.get_or_init(canvas_schema), value)
+        .expect("generated schema only uses supported keywords")
+}
+
+/// Checks a JSON document against any schema, like one generated with
+/// `schemars::schema_for!(Node)`.
+///
+/// Only the keywords used by the schemas of this crate are supported: `$ref`,
+/// `type`, `enum`, `const`, `pattern`, `format: uri`, bounds, `required`,
+/// `properties`, `additionalProperties`, `items`, `allOf`, `anyOf`, `oneOf` and
+/// `not`, along with annotations like `title`. Formats other than `uri` are
+/// annotations too. Any other keyword gives an [`InvalidSchema`] error rather than
+/// being ignored.
+pub fn validate_against(schema: &Value, value: &Value) -> Result<Vec<SchemaError>, InvalidSchema> {
+    let mut regexes = HashMap::new();
+    check_schema(schema, "#", &mut regexes)?;
+    let validator = Validator {
+        root: schema,
+        regexes,
+    };
+    Ok(validator
+        .check(schema, value, &mut Vec::new(), "#")
+        .into_iter()
+        .map(|failure| SchemaError {
+            path: path_string(&failure.path),
+            schema_path: failure.schema_path,
+            message: match failure.kind {
+                Kind::Expected { expected, found } => {
+                    format!("expected {}, found {}", expected.join(" or "), found)
+                }
+                Kind::Missing(field) => format!("missing field `{}`", field),
+                Kind::Message(message) => message,
+            },
+        })
+        .collect())
+}
+
+/// Rejects the keywords of `schema` and its subschemas which are not supported,
+/// compiling the patterns along the way
+fn check_schema(
+    schema: &Value,
+    schema_path: &str,
+    regexes: &mut HashMap<String, Regex>,
+) -> Result<(), InvalidSchema> {
+    let Value::Object(schema) = schema else {
+        return Ok(());
+    };
+    for (keyword, value) in schema {
+        let keyword_path = format!("{}/{}", schema_path, keyword);
+        let invalid = |message: String| InvalidSchema {
+            schema_path: keyword_path.clone(),
+            message,
+        };
+        match keyword.as_str() {
+            "properties" | "$defs" | "definitions" => {
+                for (name, subschema) in value.as_object().into_iter().flatten() {
+                    check_schema(subschema, &format!("{}/{}", keyword_path, name), regexes)?;
+                }
+            }
+            "allOf" | "anyOf" | "oneOf" => {
+                for (index, subschema) in value.as_array().into_iter().flatten().enumerate() {
+                    check_schema(subschema, &format!("{}/{}", keyword_path, index), regexes)?;
+                }
+            }
+            "additionalProperties" | "items" | "not" => {
+                check_schema(value, &keyword_path, regexes)?
+            }
+            "pattern" => {
+                let pattern = value.as_str().unwrap_or_default();
+                let regex = Regex::new(pattern)
+                    .map_err(|err| invalid(format!("invalid pattern: {}", err)))?;
+                regexes.insert(pattern.to_string(), regex);
+            }
+            keyword if SUPPORTED.contains(&keyword) || ANNOTATIONS.contains(&keyword) => {}
+            keyword => return Err(invalid(format!("unsupported keyword `{}`", keyword))),
+        }
+    }
+    Ok(())
+}
+
+enum Kind {
+    /// Alternatives are kept apart so that failing branches of `anyOf` can be merged
+    Expected {
+        expected: Vec<String>,
+        found: String,
+    },
+    Missing(String),
+    Message(String),
+}
+
+struct Failure {
+    path: Vec<Segment>,
+    schema_path: String,
+    keyword: &'static str,
+    kind: Kind,
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    regexes: HashMap<String, Regex>,
+}
+
+impl Validator<'_> {
+    fn check(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &mut Vec<Segment>,
+        schema_path: &str,
+    ) -> Vec<Failure> {
+        let mut failures = Vec::new();
+        let mut fail = |keyword: &'static str, kind: Kind| {
+            failures.push(Failure {
+                path: path.clone(),
+                schema_path: format!("{}/{}", schema_path, keyword),
+                keyword,
+                kind,
+            })
+        };
+        let expected = |expected: String| Kind::Expected {
+            expected: vec![expected],
+            found: value.to_string(),
+        };
+
+        let schema = match schema {
+            Value::Bool(true) => return failures,
+            Value::Object(schema) => schema,
+            _ => {
+                fail("", Kind::Message("matches nothing".to_string()));
+                return failures;
+            }
+        };
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                _ => types.as_str().into_iter().collect(),
+            };
+            if !types.iter().any(|name| has_type(value, name)) {
+                let names = types
+                    .iter()
+                    .map(|name| type_name(name).to_string())
+                    .collect();
+                fail(
+                    "type",
+                    Kind::Expected {
+                        expected: names,
+                        found: value.to_string(),
+                    },
+                );
+                return failures;
+            }
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !values.contains(value) {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                fail("enum", expected(format!("one of {}", values.join(", "))));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                fail("const", expected(constant.to_string()));
+            }
+        }
+
+        if let Value::String(s) = value {
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                if self
+                    .regexes
+                    .get(pattern)
+                    .is_some_and(|regex| !regex.is_match(s))
+                {
+                    fail(
+                        "pattern",
+                        expected(format!("a string matching {}", pattern)),
+                    );
+                }
+            }
+            if schema.get("format").and_then(Value::as_str) == Some("uri") && Url::parse(s).is_err()
+            {
+                fail("format", expected("a URI".to_string()));
+            }
+            let length = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if length < min {
+                    fail(
+                        "minLength",
+                        expected(format!("at least {} characters", min)),
+                    );
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if length > max {
+                    fail("maxLength", expected(format!("at most {} characters", max)));
+                }
+            }
+        }
+
+        if let Some(number) = value.as_f64() {
+            for (keyword, relation) in [
+                ("minimum", "greater than or equal to"),
+                ("maximum", "less than or equal to"),
+                ("exclusiveMinimum", "greater than"),
+                ("exclusiveMaximum", "less than"),
+            ] {
+                let Some(bound) = schema.get(keyword).and_then(Value::as_f64) else {
+                    continue;
+                };
+                let holds = match keyword {
+                    "minimum" => number >= bound,
+                    "maximum" => number <= bound,
+                    "exclusiveMinimum" => number > bound,
+                    _ => number < bound,
+                };
+                if !holds {
+                    fail(
+                        keyword,
+                        expected(format!("a number {} {}", relation, bound)),
+                    );
+                }
+            }
+        }
+
+        if let Value::Object(fields) = value {
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !fields.contains_key(name) {
+                        fail("required", Kind::Missing(name.to_string()));
+                    }
+                }
+            }
+        }
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match reference
+                .strip_prefix('#')
+                .and_then(|pointer| self.root.pointer(pointer))
+            {
+                Some(target) => failures.extend(self.check(target, value, path, reference)),
+                None => fail(
+                    "$ref",
+                    Kind::Message(format!("unresolved reference {}", reference)),
+                ),
+            }
+        }
+
+        match value {
+            Value::Object(fields) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (name, field) in fields {
+                    let (field_schema, field_schema_path) =
+                        match properties.and_then(|properties| properties.get(name)) {
+                            Some(field_schema) => {
+                                (field_schema, format!("{}/properties/{}", schema_path, name))
+                            }
+                            None => match schema.get("additionalProperties") {
+                                Some(field_schema) => (
+                                    field_schema,
+                                    format!("{}/additionalProperties", schema_path),
+                                ),
+                                None => continue,
+                            },
+                        };
+                    path.push(Segment::Key(name.clone()));
+                    failures.extend(self.check(field_schema, field, path, &field_schema_path));
+                    path.pop();
+                }
+            }
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    let item_schema_path = format!("{}/items", schema_path);
+                    for (index, item) in items.iter().enumerate() {
+                        path.push(Segment::Index(index));
+                        failures.extend(self.check(item_schema, item, path, &item_schema_path));
+                        path.pop();
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(branches) = schema.get("allOf").and_then(Value::as_array) {
+            for (index, branch) in branches.iter().enumerate() {
+                let branch_path = format!("{}/allOf/{}", schema_path, index);
+                failures.extend(self.check(branch, value, path, &branch_path));
+            }
+        }
+        for keyword in ["anyOf", "oneOf"] {
+            let Some(branches) = schema.get(keyword).and_then(Value::as_array) else {
+                continue;
+            };
+            let keyword_path = format!("{}/{}", schema_path, keyword);
+            let results: Vec<Vec<Failure>> = branches
+                .iter()
+                .enumerate()
+                .map(|(index, branch)| {
+                    self.check(branch, value, path, &format!("{}/{}", keyword_path, index))
+                })
+                .collect();
+            let matches = results
+                .iter()
+                .filter(|failures| failures.is_empty())
+                .count();
+            if matches == 0 {
+                failures.extend(closest(results, path, &keyword_path));
+            } else if keyword == "oneOf" && matches > 1 {
+                failures.push(Failure {
+                    path: path.clone(),
+                    schema_path: keyword_path,
+                    keyword: "oneOf",
+                    kind: Kind::Message(format!(
+                        "matches {} alternatives instead of exactly one",
+                        matches
+                    )),
+                });
+            }
+        }
+        if let Some(branch) = schema.get("not") {
+            let branch_path = format!("{}/not", schema_path);
+            if self.check(branch, value, path, &branch_path).is_empty() {
+                failures.push(Failure {
+                    path: path.clone(),
+                    schema_path: branch_path,
+                    keyword: "not",
+                    kind: Kind::Message("matches a forbidden schema".to_string()),
+                });
+            }
+        }
+        failures
+    }
+}
+
+/// Failures of the alternative the value was most likely meant to match.
+///
+/// Alternatives rejecting the type of the value, or a constant like the `type` of a
+/// node, are only kept when every alternative does. Among the remaining ones, the
+/// alternative with the fewest failures wins. When several alternatives fail on a
+/// single value, their expectations are merged.
+fn closest(results: Vec<Vec<Failure>>, path: &[Segment], schema_path: &str) -> Vec<Failure> {
+    let mismatched = |failures: &[Failure]| {
+        failures.iter().any(|failure| {
+            failure.keyword == "const" || (failure.keyword == "type" && failure.path == path)
+        })
+    };
+    let (mut candidates, mismatches): (Vec<_>, Vec<_>) = results
+        .into_iter()
+        .partition(|failures| !mismatched(failures));
+    if candidates.is_empty() {
+        candidates = mismatches
+            .into_iter()
+            .map(|failures| {
+                failures
+                    .into_iter()
+                    .filter(|failure| failure.keyword == "const" || failure.keyword == "type")
+                    .collect()
+            })
+            .collect();
+    }
+    let fewest = candidates.iter().map(Vec::len).min().unwrap_or(0);
+    candidates.retain(|failures| failures.len() == fewest);
+    if candidates.len() == 1 {
+        return candidates.pop().unwrap_or_default();
+    }
+
+    let mut merged: Option<Failure> = None;
+    for failures in &candidates {
+        let [failure] = failures.as_slice() else {
+            return candidates.swap_remove(0);
+        };
+        let Kind::Expected { expected, found } = &failure.kind else {
+            return candidates.swap_remove(0);
+        };
+        match &mut merged {
+            None => {
+                merged = Some(Failure {
+                    path: failure.path.clone(),
+                    schema_path: schema_path.to_string(),
+                    keyword: failure.keyword,
+                    kind: Kind::Expected {
+                        expected: expected.clone(),
+                        found: found.clone(),
+                    },
+                })
+            }
+            Some(first) if first.path == failure.path => {
+                if let Kind::Expected { expected: all, .. } = &mut first.kind {
+                    for expected in expected {
+                        if !all.contains(expected) {
+                            all.push(expected.clone());
+                        }
+                    }
+                }
+            }
+            Some(_) => return candidates.swap_remove(0),
+        }
+    }
+    merged.into_iter().collect()
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(name: &str) -> &str {
+    match name {
+        "object" => "an object",
+        "array" => "an array",
+        "string" => "a string",
+        "number" => "a number",
+        "integer" => "an integer",
+        "boolean" => "a boolean",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::node::Node;
+
+    fn messages(value: Value) -> Vec<String> {
+        validate_schema(&value)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn detailed_errors() {
+        assert_eq!(
+            messages(json!({"nodes": [
+                {"id": "a", "type": "text", "x": 0, "y": 0.5, "width": -1, "height": 10},
+                {"id": "b", "type": "image", "x": 0, "y": 0, "width": 1, "height": 1},
+                {"id": "c", "type": "file", "x": 0, "y": 0, "width": 1, "height": 1,
+                 "file": "a.md", "subpath": "heading", "color": "7"},
+                {"id": "d", "type": "link", "x": 0, "y": 0, "width": 1, "height": 1,
+                 "url": "not a url", "color": 3},
+                "e"
+            ]})),
+            vec![
+                "nodes[0]: missing field `text`",
+                "nodes[0].width: expected a number greater than or equal to 0, found -1",
+                "nodes[1].type: expected \"text\" or \"file\" or \"link\" or \"group\", found \"image\"",
+                "nodes[2].color: expected one of \"1\", \"2\", \"3\", \"4\", \"5\", \"6\" or a string matching ^#([0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$, found \"7\"",
+                "nodes[2].subpath: expected a string matching ^#, found \"heading\"",
+                "nodes[3].color: expected a string or null, found 3",
+                "nodes[3].url: expected a URI, found \"not a url\"",
+                "nodes[4]: expected an object, found \"e\"",
+            ]
+        );
+        assert_eq!(
+            validate_schema(&json!({"edges": {}}))[0].schema_path,
+            "#/properties/edges/type"
+        );
+    }
+
+    #[test]
+    fn schema_matches_parsing() {
+        let canvas: JsonCanvas = r##"{"nodes":[
+            {"id":"a","type":"group","x":0,"y":0,"width":10,"height":10,"background":"a.png","color":"#abc"},
+            {"id":"b","type":"link","x":0,"y":0,"width":10,"height":10,"url":"https://jsoncanvas.org"}
+        ],"edges":[{"id":"e","fromNode":"a","toNode":"b","toSide":"left","color":"2"}]}"##
+            .parse()
+            .unwrap();
+        assert!(validate_schema(&serde_json::to_value(&canvas).unwrap()).is_empty());
+
+        let node = schemars::schema_for!(Node).to_value();
+        assert!(validate_against(&node, &json!({"id": "a"}))
+            .unwrap()
+            .iter()
+            .any(|error| error.message == "missing field `type`"));
+    }
+
+    #[test]
+    fn unsupported_keywords_are_rejected() {
+        let schema = json!({
+            "type": "object",
+            "title": "Tags",
+            "properties": {"tags": {"type": "array", "uniqueItems": true}}
+        });
+        let error = validate_against(&schema, &json!({"tags": ["a", "a"]})).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "#/properties/tags/uniqueItems: unsupported keyword `uniqueItems`"
+        );
+
+        let schema = json!({"anyOf": [{"type": "string", "pattern": "(a"}]});
+        let error = validate_against(&schema, &json!("a")).unwrap_err();
+        assert_eq!(error.schema_path, "#/anyOf/0/pattern");
+        assert!(error.message.starts_with("invalid pattern"));
+    }
+}
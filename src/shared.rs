@@ -0,0 +1,315 @@
+//! Canvas shared between threads, read concurrently and written one at a time.
+//!
+//! Every write changing the canvas increments its version, so that a thread can
+//! check that the canvas did not change since it read it before writing, with
+//! [`SharedCanvas::write_if_unchanged`].
+
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{JsonCanvas, JsonCanvasError};
+
+#[derive(Debug)]
+struct Inner {
+    canvas: RwLock<JsonCanvas>,
+    /// Only changed while the canvas is locked for writing
+    version: AtomicU64,
+}
+
+/// Handle to a canvas shared between threads. Clones refer to the same canvas.
+///
+/// A thread panicking while writing does not lock the others out: the canvas stays
+/// readable with the changes made before the panic, under a new version. A patch
+/// or transaction interrupted by a panic may be left half-applied.
+///
+/// ```
+/// use jsoncanvas::shared::SharedCanvas;
+/// use jsoncanvas::{JsonCanvas, TextNode};
+///
+/// let shared = SharedCanvas::new(JsonCanvas::default());
+/// let version = shared.read().version();
+/// std::thread::scope(|scope| {
+///     scope.spawn(|| {
+///         let node = TextNode::new("a".parse().unwrap(), 0, 0, 10, 10, None, "A".into());
+///         shared.write().add_node(node.into())
+///     });
+/// });
+/// assert_eq!(shared.read().get_nodes().len(), 1);
+/// assert!(shared.write_if_unchanged(version).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedCanvas {
+    inner: Arc<Inner>,
+}
+
+impl SharedCanvas {
+    pub fn new(mut canvas: JsonCanvas) -> Self {
+        canvas.set_tracked(true);
+        SharedCanvas {
+            inner: Arc::new(Inner {
+                canvas: RwLock::new(canvas),
+                version: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Current version, without waiting for the writers
+    pub fn version(&self) -> u64 {
+        self.inner.version.load(Ordering::Acquire)
+    }
+
+    /// Waits until no thread is writing, then locks the canvas for reading
+    pub fn read(&self) -> CanvasReadGuard<'_> {
+        let guard = self
+            .inner
+            .canvas
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        CanvasReadGuard {
+            guard,
+            version: self.version(),
+        }
+    }
+
+    /// Waits until no thread is reading or writing, then locks the canvas for writing
+    pub fn write(&self) -> CanvasWriteGuard<'_> {
+        let guard = self
+            .inner
+            .canvas
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let changes = guard.changes();
+        CanvasWriteGuard {
+            guard,
+            version: &self.inner.version,
+            changes,
+        }
+    }
+
+    /// Locks the canvas for writing if it is still at `version`, or fails with
+    /// [`JsonCanvasError::VersionMismatch`]
+    pub fn write_if_unchanged(
+        &self,
+        version: u64,
+    ) -> Result<CanvasWriteGuard<'_>, JsonCanvasError> {
+        let guard = self.write();
+        let actual = guard.version();
+        if actual != version {
+            return Err(JsonCanvasError::VersionMismatch {
+                expected: version,
+                actual,
+            });
+        }
+        Ok(guard)
+    }
+
+    /// Returns the canvas if this is the last handle to it
+    pub fn try_unwrap(self) -> Result<JsonCanvas, SharedCanvas> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => {
+                let mut canvas = inner
+                    .canvas
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner);
+                canvas.set_tracked(false);
+                Ok(canvas)
+            }
+            Err(inner) => Err(SharedCanvas { inner }),
+        }
+    }
+}
+
+impl Default for SharedCanvas {
+    fn default() -> Self {
+        SharedCanvas::new(JsonCanvas::default())
+    }
+}
+
+impl From<JsonCanvas> for SharedCanvas {
+    fn from(canvas: JsonCanvas) -> Self {
+        SharedCanvas::new(canvas)
+    }
+}
+
+/// Read access to a [`SharedCanvas`], blocking the writers until dropped
+pub struct CanvasReadGuard<'a> {
+    guard: RwLockReadGuard<'a, JsonCanvas>,
+    version: u64,
+}
+
+impl CanvasReadGuard<'_> {
+    /// Version of the canvas being read
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl Deref for CanvasReadGuard<'_> {
+    type Target = JsonCanvas;
+
+    fn deref(&self) -> &JsonCanvas {
+        &self.guard
+    }
+}
+
+/// Write access to a [`SharedCanvas`], blocking the other threads until dropped.
+///
+/// The version is incremented when the guard is dropped, if the canvas changed.
+/// Failed edits and lookups do not count, but the maps returned by the deprecated
+/// [`JsonCanvas::get_mut_nodes`] and [`JsonCanvas::get_mut_edges`] cannot be
+/// checked, so taking one counts as a change.
+pub struct CanvasWriteGuard<'a> {
+    guard: RwLockWriteGuard<'a, JsonCanvas>,
+    version: &'a AtomicU64,
+    /// Changes of the canvas when the guard was created
+    changes: u64,
+}
+
+impl CanvasWriteGuard<'_> {
+    /// Version of the canvas, including the changes made through this guard
+    pub fn version(&self) -> u64 {
+        let version = self.version.load(Ordering::Acquire);
+        if self.is_changed() {
+            version + 1
+        } else {
+            version
+        }
+    }
+
+    fn is_changed(&self) -> bool {
+        self.guard.changes() != self.changes
+    }
+}
+
+impl Deref for CanvasWriteGuard<'_> {
+    type Target = JsonCanvas;
+
+    fn deref(&self) -> &JsonCanvas {
+        &self.guard
+    }
+}
+
+impl DerefMut for CanvasWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut JsonCanvas {
+        &mut self.guard
+    }
+}
+
+impl Drop for CanvasWriteGuard<'_> {
+    fn drop(&mut self) {
+        if self.is_changed() {
+            self.version.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{GenericNodeInfo, TextNode};
+    use crate::NodeId;
+
+    fn text(id: &str) -> crate::Node {
+        TextNode::new(id.parse().unwrap(), 0, 0, 100, 100, None, id.to_string()).into()
+    }
+
+    #[test]
+    fn concurrent_readers_and_writers() {
+        let shared = SharedCanvas::default();
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let shared = shared.clone();
+                scope.spawn(move || {
+                    for index in 0..25 {
+                        let id = format!("{}-{}", writer, index);
+                        shared.write().add_node(text(&id)).unwrap();
+                    }
+                });
+            }
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..25 {
+                        let canvas = shared.read();
+                        assert_eq!(canvas.get_nodes().len() as u64, canvas.version());
+                    }
+                });
+            }
+        });
+        assert_eq!(shared.version(), 100);
+        assert_eq!(shared.read().get_nodes().len(), 100);
+    }
+
+    #[test]
+    fn detect_changes() {
+        let shared = SharedCanvas::new(JsonCanvas::default());
+        let version = shared.read().version();
+        let mut canvas = shared.write_if_unchanged(version).unwrap();
+        assert_eq!(canvas.version(), 0);
+        canvas.add_node(text("a")).unwrap();
+        canvas.node_mut(&"a".parse().unwrap()).unwrap().set_x(10);
+        assert_eq!(canvas.version(), 1);
+        drop(canvas);
+        assert_eq!(shared.version(), 1);
+
+        let err = shared.write_if_unchanged(version).err().unwrap();
+        assert!(matches!(
+            err,
+            JsonCanvasError::VersionMismatch {
+                expected: 0,
+                actual: 1
+            }
+        ));
+        // Lookups, failed edits and edits leaving the canvas as it was do not
+        // change the version
+        let a: NodeId = "a".parse().unwrap();
+        assert_eq!(shared.read().node(&a).unwrap().get_x(), 10);
+        let mut canvas = shared.write();
+        assert!(canvas.add_node(text("a")).is_err());
+        assert!(canvas.remove_edge(&"e".parse().unwrap()).is_err());
+        canvas.node_mut(&a).unwrap().set_x(10);
+        canvas.move_node(&a, 0).unwrap();
+        let result = canvas.transaction(|tx| {
+            tx.add_node(text("b"))?;
+            tx.remove_node(&"c".parse().unwrap())
+        });
+        assert!(result.is_err());
+        drop(canvas);
+        assert_eq!(shared.version(), 1);
+        shared.write().get_node(a.clone());
+        assert_eq!(shared.version(), 1);
+        // Mutable references cannot be checked
+        #[allow(deprecated)]
+        shared.write().get_mut_nodes();
+        assert_eq!(shared.version(), 2);
+
+        let other = shared.clone();
+        let shared = shared.try_unwrap().unwrap_err();
+        drop(other);
+        assert_eq!(shared.try_unwrap().unwrap().get_nodes().len(), 1);
+    }
+
+    #[test]
+    fn panicking_writer() {
+        let shared = SharedCanvas::default();
+        let result = std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let mut canvas = shared.write();
+                    canvas.add_node(text("a")).unwrap();
+                    panic!("writer failed");
+                })
+                .join()
+        });
+        assert!(result.is_err());
+
+        // The changes made before the panic are kept, under a new version
+        assert_eq!(shared.version(), 1);
+        let canvas = shared.read();
+        assert!(canvas.node(&"a".parse().unwrap()).is_some());
+        assert_eq!(canvas.version(), 1);
+        drop(canvas);
+        shared.write().add_node(text("b")).unwrap();
+        assert_eq!(shared.version(), 2);
+    }
+}
@@ -0,0 +1,443 @@
+//! Persistent canvases, for undo stacks, history browsing and concurrent readers.
+//!
+//! A [`CanvasSnapshot`] shares its structure with the snapshots it was cloned from:
+//! cloning is O(1), and adding or updating an item copies only the path to it.
+//! Removing an item also looks it up in the z-order, which is O(n).
+
+use std::collections::BTreeSet;
+
+use imbl::ordmap::DiffItem;
+use imbl::{OrdMap, Vector};
+
+use crate::edge::Edge;
+use crate::node::{GenericNodeInfo, Node};
+use crate::patch::{CanvasPatch, PatchOp};
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CanvasSnapshot {
+    nodes: OrdMap<NodeId, Node>,
+    edges: OrdMap<EdgeId, Edge>,
+    /// Z-order of the nodes
+    node_order: Vector<NodeId>,
+    edge_order: Vector<EdgeId>,
+}
+
+/// Differences between the items of two snapshots
+struct Changes<K, V> {
+    updated: Vec<(V, V)>,
+    /// In the order of the new snapshot
+    added: Vec<V>,
+    /// With their index in the old snapshot, from the last to the first
+    removed: Vec<(usize, V)>,
+    /// Order of the new snapshot, if it differs from the old order with the added
+    /// items appended
+    reordered: Option<(Vec<K>, Vec<K>)>,
+}
+
+fn changes<K: Ord + Clone, V: Clone + PartialEq>(
+    (old, old_order): (&OrdMap<K, V>, &Vector<K>),
+    (new, new_order): (&OrdMap<K, V>, &Vector<K>),
+) -> Changes<K, V> {
+    let mut updated = Vec::new();
+    let mut added_keys = BTreeSet::new();
+    // The order lists every key, so nothing was added or removed either
+    if old_order.ptr_eq(new_order) {
+        for item in old.diff(new) {
+            if let DiffItem::Update {
+                old: (_, from),
+                new: (_, to),
+            } = item
+            {
+                updated.push((from.clone(), to.clone()));
+            }
+        }
+        return Changes {
+            updated,
+            added: Vec::new(),
+            removed: Vec::new(),
+            reordered: None,
+        };
+    }
+    let mut removed_keys = BTreeSet::new();
+    for item in old.diff(new) {
+        match item {
+            DiffItem::Add(key, _) => {
+                added_keys.insert(key);
+            }
+            DiffItem::Remove(key, _) => {
+                removed_keys.insert(key);
+            }
+            DiffItem::Update {
+                old: (_, from),
+                new: (_, to),
+            } => {
+                if from != to {
+                    updated.push((from.clone(), to.clone()));
+                }
+            }
+        }
+    }
+
+    let mut added = Vec::new();
+    if !added_keys.is_empty() {
+        added = new_order
+            .iter()
+            .filter(|key| added_keys.contains(key))
+            .map(|key| new[key].clone())
+            .collect();
+    }
+    let mut removed: Vec<(usize, V)> = Vec::new();
+    if !removed_keys.is_empty() {
+        removed = old_order
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| removed_keys.contains(key))
+            .map(|(index, key)| (index, old[key].clone()))
+            .collect();
+    }
+    removed.reverse();
+
+    let reordered = if added_keys.is_empty() && removed_keys.is_empty() {
+        (old_order != new_order).then(|| old_order.iter().cloned().collect())
+    } else {
+        let appended: Vec<K> = old_order
+            .iter()
+            .filter(|key| !removed_keys.contains(key))
+            .chain(new_order.iter().filter(|key| added_keys.contains(key)))
+            .cloned()
+            .collect();
+        (!appended.iter().eq(new_order.iter())).then_some(appended)
+    };
+    Changes {
+        updated,
+        added,
+        removed,
+        reordered: reordered.map(|from| (from, new_order.iter().cloned().collect())),
+    }
+}
+
+impl CanvasSnapshot {
+    pub fn get_node(&self, id: &NodeId) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    pub fn get_edge(&self, id: &EdgeId) -> Option<&Edge> {
+        self.edges.get(id)
+    }
+
+    /// Nodes in z-order
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.node_order.iter().map(|id| &self.nodes[id])
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edge_order.iter().map(|id| &self.edges[id])
+    }
+
+    /// Changes a node, copying it if it is shared with another snapshot.
+    ///
+    /// The ID of the node cannot be changed, and is restored after `update`.
+    pub fn update_node(
+        &mut self,
+        id: &NodeId,
+        update: impl FnOnce(&mut Node),
+    ) -> Result<(), JsonCanvasError> {
+        let node = self
+            .nodes
+            .get_mut(id)
+            .ok_or_else(|| JsonCanvasError::NodeNotExists(id.clone()))?;
+        update(node);
+        node.set_id(id.clone());
+        Ok(())
+    }
+
+    /// Changes an edge, copying it if it is shared with another snapshot.
+    ///
+    /// The ID of the edge cannot be changed, and the change is discarded if the edge
+    /// would point to a missing node.
+    pub fn update_edge(
+        &mut self,
+        id: &EdgeId,
+        update: impl FnOnce(&mut Edge),
+    ) -> Result<(), JsonCanvasError> {
+        let mut edge = self
+            .edges
+            .get(id)
+            .ok_or_else(|| JsonCanvasError::EdgeNotExists(id.clone()))?
+            .clone();
+        update(&mut edge);
+        edge.id = id.clone();
+        for node in [edge.from_node(), edge.to_node()] {
+            if !self.nodes.contains_key(node) {
+                return Err(JsonCanvasError::NodeNotExists(node.clone()));
+            }
+        }
+        self.edges.insert(id.clone(), edge);
+        Ok(())
+    }
+
+    pub fn add_node(&mut self, node: Node) -> Result<(), JsonCanvasError> {
+        if self.nodes.contains_key(node.id()) {
+            return Err(JsonCanvasError::NodeExists(node.id().clone()));
+        }
+        self.node_order.push_back(node.id().clone());
+        self.nodes.insert(node.id().clone(), node);
+        Ok(())
+    }
+
+    pub fn add_edge(&mut self, edge: Edge) -> Result<(), JsonCanvasError> {
+        if self.edges.contains_key(edge.id()) {
+            return Err(JsonCanvasError::EdgeExists(edge.id().clone()));
+        }
+        for node in [edge.from_node(), edge.to_node()] {
+            if !self.nodes.contains_key(node) {
+                return Err(JsonCanvasError::NodeNotExists(node.clone()));
+            }
+        }
+        self.edge_order.push_back(edge.id().clone());
+        self.edges.insert(edge.id().clone(), edge);
+        Ok(())
+    }
+
+    /// Removes a node, along with the edges connected to it
+    pub fn remove_node(&mut self, id: &NodeId) -> Result<(Node, Vec<Edge>), JsonCanvasError> {
+        let node = self
+            .nodes
+            .remove(id)
+            .ok_or_else(|| JsonCanvasError::NodeNotExists(id.clone()))?;
+        if let Some(index) = self.node_order.index_of(id) {
+            self.node_order.remove(index);
+        }
+        let connected: Vec<EdgeId> = self
+            .edges()
+            .filter(|edge| edge.from_node() == id || edge.to_node() == id)
+            .map(|edge| edge.id().clone())
+            .collect();
+        let edges = connected
+            .iter()
+            .map(|edge| self.remove_edge(edge))
+            .collect::<Result<_, _>>()?;
+        Ok((node, edges))
+    }
+
+    pub fn remove_edge(&mut self, id: &EdgeId) -> Result<Edge, JsonCanvasError> {
+        let edge = self
+            .edges
+            .remove(id)
+            .ok_or_else(|| JsonCanvasError::EdgeNotExists(id.clone()))?;
+        if let Some(index) = self.edge_order.index_of(id) {
+            self.edge_order.remove(index);
+        }
+        Ok(edge)
+    }
+
+    /// Builds the patch turning this snapshot into `other`, like
+    /// [`CanvasPatch::between`].
+    ///
+    /// Items shared by both snapshots are not compared. The z-orders are compared
+    /// in O(n), unless they are shared too, i.e. when no item was added, removed or
+    /// moved.
+    pub fn diff(&self, other: &CanvasSnapshot) -> CanvasPatch {
+        let nodes = changes(
+            (&self.nodes, &self.node_order),
+            (&other.nodes, &other.node_order),
+        );
+        let edges = changes(
+            (&self.edges, &self.edge_order),
+            (&other.edges, &other.edge_order),
+        );
+
+        // Same order as `CanvasPatch::between`: edges are removed first and nodes
+        // last, and updated edges may point to added nodes
+        let mut ops: Vec<PatchOp> = edges
+            .removed
+            .into_iter()
+            .map(|(index, edge)| PatchOp::RemoveEdge {
+                edge,
+                index: Some(index),
+            })
+            .collect();
+        ops.extend(
+            nodes
+                .updated
+                .into_iter()
+                .map(|(from, to)| PatchOp::UpdateNode { from, to }),
+        );
+        ops.extend(
+            nodes
+                .added
+                .into_iter()
+                .map(|node| PatchOp::AddNode { node, index: None }),
+        );
+        ops.extend(
+            edges
+                .updated
+                .into_iter()
+                .map(|(from, to)| PatchOp::UpdateEdge { from, to }),
+        );
+        ops.extend(
+            edges
+                .added
+                .into_iter()
+                .map(|edge| PatchOp::AddEdge { edge, index: None }),
+        );
+        ops.extend(
+            nodes
+                .removed
+                .into_iter()
+                .map(|(index, node)| PatchOp::RemoveNode {
+                    node,
+                    index: Some(index),
+                }),
+        );
+        if let Some((from, to)) = nodes.reordered {
+            ops.push(PatchOp::ReorderNodes { from, to });
+        }
+        if let Some((from, to)) = edges.reordered {
+            ops.push(PatchOp::ReorderEdges { from, to });
+        }
+        CanvasPatch::new(ops)
+    }
+
+    pub fn to_canvas(&self) -> JsonCanvas {
+        let mut canvas = JsonCanvas::default();
+        let nodes = canvas.unobserved_nodes_mut();
+        for node in self.nodes() {
+            nodes.insert(node.id().clone(), node.clone());
+        }
+        let edges = canvas.unobserved_edges_mut();
+        for edge in self.edges() {
+            edges.insert(edge.id().clone(), edge.clone());
+        }
+        canvas
+    }
+}
+
+impl From<&JsonCanvas> for CanvasSnapshot {
+    fn from(canvas: &JsonCanvas) -> Self {
+        CanvasSnapshot {
+            nodes: canvas
+                .get_nodes()
+                .iter()
+                .map(|(id, node)| (id.clone(), node.clone()))
+                .collect(),
+            edges: canvas
+                .get_edges()
+                .iter()
+                .map(|(id, edge)| (id.clone(), edge.clone()))
+                .collect(),
+            node_order: canvas.nodes().map(|node| node.id().clone()).collect(),
+            edge_order: canvas.edges().map(|edge| edge.id().clone()).collect(),
+        }
+    }
+}
+
+impl JsonCanvas {
+    /// Copies the whole canvas into a [`CanvasSnapshot`], in O(n). Later versions
+    /// should be derived from the snapshot to share its structure.
+    pub fn snapshot(&self) -> CanvasSnapshot {
+        self.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::PresetColor;
+    use crate::node::TextNode;
+
+    fn text(id: &str) -> Node {
+        TextNode::new(id.parse().unwrap(), 0, 0, 100, 100, None, id.to_string()).into()
+    }
+
+    fn edge(id: &str, from: &str, to: &str) -> Edge {
+        Edge::new(
+            id.parse().unwrap(),
+            from.parse().unwrap(),
+            None,
+            None,
+            to.parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn edit_without_changing_snapshots() {
+        let mut first = CanvasSnapshot::default();
+        first.add_node(text("a")).unwrap();
+        first.add_node(text("b")).unwrap();
+        first.add_edge(edge("e", "a", "b")).unwrap();
+
+        let mut second = first.clone();
+        second
+            .update_node(&"a".parse().unwrap(), |node| {
+                node.set_color(Some(PresetColor::Red.into()))
+            })
+            .unwrap();
+        second.remove_node(&"b".parse().unwrap()).unwrap();
+        assert!(matches!(
+            second.add_edge(edge("f", "a", "b")),
+            Err(JsonCanvasError::NodeNotExists(_))
+        ));
+        first
+            .update_node(&"b".parse().unwrap(), |node| {
+                *node = text("c");
+            })
+            .unwrap();
+        assert_eq!(first.nodes().nth(1).unwrap().id().as_str(), "b");
+        assert!(matches!(
+            first.update_edge(&"e".parse().unwrap(), |edge| {
+                edge.set_to("c".parse().unwrap(), None, None);
+            }),
+            Err(JsonCanvasError::NodeNotExists(_))
+        ));
+        assert_eq!(
+            first.get_edge(&"e".parse().unwrap()),
+            Some(&edge("e", "a", "b"))
+        );
+
+        assert_eq!(first.nodes().count(), 2);
+        assert_eq!(first.get_node(&"a".parse().unwrap()), Some(&text("a")));
+        assert_eq!(second.nodes().count(), 1);
+        assert_eq!(second.edges().count(), 0);
+        assert_eq!(first.to_canvas().snapshot(), first);
+    }
+
+    #[test]
+    fn diff_applies_to_canvas() {
+        let mut old = CanvasSnapshot::default();
+        for id in ["a", "b", "c"] {
+            old.add_node(text(id)).unwrap();
+        }
+        old.add_edge(edge("e", "a", "b")).unwrap();
+        old.add_edge(edge("f", "b", "c")).unwrap();
+
+        let mut new = old.clone();
+        assert!(old.diff(&new).is_empty());
+        new.remove_node(&"a".parse().unwrap()).unwrap();
+        new.add_node(text("d")).unwrap();
+        new.add_edge(edge("g", "d", "b")).unwrap();
+        new.update_edge(&"f".parse().unwrap(), |edge| {
+            edge.set_label("label".to_string());
+        })
+        .unwrap();
+
+        let patch = old.diff(&new);
+        let mut canvas = old.to_canvas();
+        canvas.apply(&patch).unwrap();
+        assert_eq!(canvas, new.to_canvas());
+        canvas.apply(&patch.invert()).unwrap();
+        assert_eq!(canvas, old.to_canvas());
+
+        let mut reordered = new.to_canvas();
+        reordered.move_node(&"b".parse().unwrap(), 2).unwrap();
+        let reordered = reordered.snapshot();
+        let mut canvas = new.to_canvas();
+        canvas.apply(&new.diff(&reordered)).unwrap();
+        assert_eq!(canvas, reordered.to_canvas());
+    }
+}
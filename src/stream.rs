@@ -0,0 +1,522 @@
+//! Reading and writing canvases one node or edge at a time, for documents too large
+//! to hold in memory.
+//!
+//! Memory use is bounded by the size of the largest node or edge. Since the whole
+//! canvas is never known, the reader does not check that IDs are unique or that
+//! edges connect existing nodes.
+
+use std::io::{BufRead, BufReader, Read, Write};
+
+use serde_json::Value;
+
+use crate::edge::Edge;
+use crate::format::{to_value, FormatOptions, Layout, Writer};
+use crate::node::Node;
+use crate::parse::{diagnose_item, locate, path_string, ParseError, Segment};
+use crate::JsonCanvasError;
+
+/// Node or edge read from a stream
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum CanvasItem {
+    Node(Node),
+    Edge(Edge),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start,
+    /// Before a key of the root object
+    Key,
+    /// Before an item of the `nodes` or `edges` array
+    Item {
+        nodes: bool,
+        index: usize,
+    },
+    /// After a member of the root object
+    Member,
+    Done,
+}
+
+/// Iterator over the nodes and edges of a canvas, in the order of the document
+///
+/// ```
+/// use jsoncanvas::stream::{CanvasItem, CanvasReader};
+///
+/// let source = r#"{"nodes":[{"id":"a","type":"text","x":0,"y":0,"width":10,"height":10,"text":"A"}]}"#;
+/// for item in CanvasReader::new(source.as_bytes()) {
+///     assert!(matches!(item.unwrap(), CanvasItem::Node(_)));
+/// }
+/// ```
+pub struct CanvasReader<R> {
+    reader: BufReader<R>,
+    state: State,
+    /// Line and column of the next byte, counted from 1
+    line: usize,
+    column: usize,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> CanvasReader<R> {
+    pub fn new(reader: R) -> Self {
+        CanvasReader {
+            reader: BufReader::new(reader),
+            state: State::Start,
+            line: 1,
+            column: 1,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn error(&self, path: &str, reason: String) -> JsonCanvasError {
+        JsonCanvasError::ParseError(ParseError {
+            path: path.to_string(),
+            id: None,
+            line: self.line,
+            column: self.column,
+            reason,
+        })
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, JsonCanvasError> {
+        let buffer = self.reader.fill_buf().map_err(JsonCanvasError::StreamIo)?;
+        Ok(buffer.first().copied())
+    }
+
+    /// Consumes the next byte, copying it to the buffer if `capture`
+    fn bump(&mut self, capture: bool) -> Result<u8, JsonCanvasError> {
+        let Some(byte) = self.peek()? else {
+            return Err(self.error("", "unexpected end of file".to_string()));
+        };
+        self.reader.consume(1);
+        if capture {
+            self.buffer.push(byte);
+        }
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Ok(byte)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), JsonCanvasError> {
+        while matches!(self.peek()?, Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.bump(false)?;
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: &[u8], path: &str) -> Result<u8, JsonCanvasError> {
+        self.skip_whitespace()?;
+        match self.peek()? {
+            Some(byte) if expected.contains(&byte) => self.bump(false),
+            found => {
+                let expected: Vec<String> = expected
+                    .iter()
+                    .map(|byte| format!("`{}`", *byte as char))
+                    .collect();
+                let found = match found {
+                    Some(byte) => format!("`{}`", byte as char),
+                    None => "end of file".to_string(),
+                };
+                Err(self.error(
+                    path,
+                    format!("expected {}, found {}", expected.join(" or "), found),
+                ))
+            }
+        }
+    }
+
+    /// Reads the rest of a string whose opening quote was consumed
+    fn string(&mut self, capture: bool) -> Result<(), JsonCanvasError> {
+        loop {
+            match self.bump(capture)? {
+                b'\\' => {
+                    self.bump(capture)?;
+                }
+                b'"' => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads a value, copying it to the buffer if `capture`.
+    ///
+    /// Only the brackets are checked, the rest of a value is checked when parsing it.
+    fn value(&mut self, capture: bool) -> Result<(), JsonCanvasError> {
+        self.skip_whitespace()?;
+        // Closing brackets expected for the arrays and objects being read
+        let mut closing = Vec::new();
+        loop {
+            match self.peek()? {
+                Some(b'"') => {
+                    self.bump(capture)?;
+                    self.string(capture)?;
+                }
+                Some(b'{') => {
+                    self.bump(capture)?;
+                    closing.push(b'}');
+                }
+                Some(b'[') => {
+                    self.bump(capture)?;
+                    closing.push(b']');
+                }
+                Some(byte @ (b'}' | b']')) if !closing.is_empty() => {
+                    let expected = closing.pop().unwrap();
+                    if byte != expected {
+                        return Err(self.error(
+                            "",
+                            format!("expected `{}`, found `{}`", expected as char, byte as char),
+                        ));
+                    }
+                    self.bump(capture)?;
+                }
+                Some(b' ' | b'\t' | b'\n' | b'\r' | b',' | b':') if !closing.is_empty() => {
+                    self.bump(capture)?;
+                }
+                None if !closing.is_empty() => {
+                    return Err(self.error("", "unexpected end of file".to_string()));
+                }
+                Some(b' ' | b'\t' | b'\n' | b'\r' | b',' | b'}' | b']') | None => break,
+                Some(_) => {
+                    self.bump(capture)?;
+                    continue;
+                }
+            }
+            if closing.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the next node or edge
+    fn item(&mut self, nodes: bool, index: usize) -> Result<CanvasItem, JsonCanvasError> {
+        let key = if nodes { "nodes" } else { "edges" };
+        self.skip_whitespace()?;
+        let (line, column) = (self.line, self.column);
+        self.buffer.clear();
+        self.value(true)?;
+        let parsed = if nodes {
+            serde_json::from_slice(&self.buffer).map(CanvasItem::Node)
+        } else {
+            serde_json::from_slice(&self.buffer).map(CanvasItem::Edge)
+        };
+        let err = match parsed {
+            Ok(item) => return Ok(item),
+            Err(err) => err,
+        };
+
+        // Same diagnostics as parsing a whole document, relative to the item
+        let mut path = vec![Segment::Key(key.to_string()), Segment::Index(index)];
+        let mut error = ParseError::from(err);
+        let mut position = (error.line, error.column);
+        if let Ok(value) = serde_json::from_slice::<Value>(&self.buffer) {
+            if let Some((field, reason)) = diagnose_item(key, &value) {
+                let text = String::from_utf8_lossy(&self.buffer);
+                position = match &field {
+                    Some(field) => locate(&text, &[Segment::Key(field.clone())]),
+                    None => (1, 1),
+                };
+                path.extend(field.map(Segment::Key));
+                error.reason = reason;
+            }
+            error.id = value.get("id").and_then(Value::as_str).map(str::to_string);
+        }
+        error.path = path_string(&path);
+        error.line = line + position.0.max(1) - 1;
+        error.column = match position.0 {
+            0 | 1 => column + position.1.max(1) - 1,
+            _ => position.1,
+        };
+        Err(JsonCanvasError::ParseError(error))
+    }
+
+    fn advance(&mut self) -> Result<Option<CanvasItem>, JsonCanvasError> {
+        loop {
+            match self.state {
+                State::Start => {
+                    self.expect(b"{", "")?;
+                    self.skip_whitespace()?;
+                    self.state = match self.peek()? {
+                        Some(b'}') => State::Member,
+                        _ => State::Key,
+                    };
+                }
+                State::Key => {
+                    self.expect(b"\"", "")?;
+                    self.buffer.clear();
+                    self.buffer.push(b'"');
+                    self.string(true)?;
+                    let key: String = serde_json::from_slice(&self.buffer)
+                        .map_err(|err| self.error("", err.to_string()))?;
+                    self.expect(b":", &key)?;
+                    self.skip_whitespace()?;
+                    if key != "nodes" && key != "edges" {
+                        self.value(false)?;
+                        self.state = State::Member;
+                        continue;
+                    }
+                    self.expect(b"[", &key)?;
+                    self.skip_whitespace()?;
+                    if self.peek()? == Some(b']') {
+                        self.bump(false)?;
+                        self.state = State::Member;
+                    } else {
+                        self.state = State::Item {
+                            nodes: key == "nodes",
+                            index: 0,
+                        };
+                    }
+                }
+                State::Item { nodes, index } => {
+                    let item = self.item(nodes, index)?;
+                    self.state = match self.expect(b",]", "")? {
+                        b',' => State::Item {
+                            nodes,
+                            index: index + 1,
+                        },
+                        _ => State::Member,
+                    };
+                    return Ok(Some(item));
+                }
+                State::Member => {
+                    if self.expect(b",}", "")? == b',' {
+                        self.state = State::Key;
+                        continue;
+                    }
+                    self.skip_whitespace()?;
+                    if self.peek()?.is_some() {
+                        return Err(self.error("", "trailing characters".to_string()));
+                    }
+                    self.state = State::Done;
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for CanvasReader<R> {
+    type Item = Result<CanvasItem, JsonCanvasError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.advance();
+        if result.is_err() {
+            self.state = State::Done;
+        }
+        result.transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Empty,
+    Nodes,
+    Edges,
+}
+
+/// Writes a canvas one node or edge at a time. All the nodes must be written before
+/// the edges, and [`CanvasWriter::finish`] must be called to complete the document.
+///
+/// The output is the same as [`crate::JsonCanvas::to_string_with`], except that
+/// [`crate::format::Order`] is ignored.
+pub struct CanvasWriter<W: Write> {
+    writer: W,
+    options: FormatOptions,
+    section: Section,
+}
+
+impl<W: Write> CanvasWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, FormatOptions::default())
+    }
+
+    pub fn with_options(writer: W, options: FormatOptions) -> Self {
+        CanvasWriter {
+            writer,
+            options,
+            section: Section::Empty,
+        }
+    }
+
+    /// Writes what comes before the next item of `section`
+    fn open(&mut self, writer: &mut Writer, section: Section) {
+        let multiline = self.options.layout != Layout::Compact;
+        if self.section == section {
+            writer.out.push(',');
+        } else {
+            match self.section {
+                Section::Empty => writer.out.push('{'),
+                _ => {
+                    if multiline {
+                        writer.newline(1);
+                    }
+                    writer.out.push_str("],");
+                }
+            }
+            if multiline {
+                writer.newline(1);
+            }
+            writer.key(if section == Section::Nodes {
+                "nodes"
+            } else {
+                "edges"
+            });
+            writer.out.push('[');
+            self.section = section;
+        }
+        if multiline {
+            writer.newline(2);
+        }
+    }
+
+    fn write_item(
+        &mut self,
+        section: Section,
+        item: Value,
+        fields: &[&str],
+    ) -> Result<(), JsonCanvasError> {
+        let options = self.options;
+        let mut writer = Writer {
+            options: &options,
+            out: String::new(),
+        };
+        self.open(&mut writer, section);
+        writer.item(&item, fields, 2);
+        self.writer
+            .write_all(writer.out.as_bytes())
+            .map_err(JsonCanvasError::StreamIo)?;
+        Ok(())
+    }
+
+    pub fn write_node(&mut self, node: &Node) -> Result<(), JsonCanvasError> {
+        if self.section == Section::Edges {
+            return Err(JsonCanvasError::NodeAfterEdges);
+        }
+        let fields = self.options.key_order.node_fields();
+        self.write_item(Section::Nodes, to_value(node), &fields)
+    }
+
+    pub fn write_edge(&mut self, edge: &Edge) -> Result<(), JsonCanvasError> {
+        let fields = self.options.key_order.edge_fields();
+        self.write_item(Section::Edges, to_value(edge), &fields)
+    }
+
+    /// Completes the document, returning the underlying writer
+    pub fn finish(mut self) -> Result<W, JsonCanvasError> {
+        let options = self.options;
+        let mut writer = Writer {
+            options: &options,
+            out: String::new(),
+        };
+        if self.section == Section::Empty {
+            writer.out.push('{');
+        } else {
+            if options.layout != Layout::Compact {
+                writer.newline(1);
+            }
+            writer.out.push(']');
+            if options.layout != Layout::Compact {
+                writer.newline(0);
+            }
+        }
+        writer.out.push('}');
+        self.writer
+            .write_all(writer.out.as_bytes())
+            .map_err(JsonCanvasError::StreamIo)?;
+        self.writer.flush().map_err(JsonCanvasError::StreamIo)?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonCanvas;
+
+    const SOURCE: &str = "{\"version\": {\"a\": [1, \"]\"]},\n \"nodes\": [\n  {\"id\": \"a\", \"type\": \"text\", \"x\": 0, \"y\": 0, \"width\": 10, \"height\": 10, \"text\": \"{[\\\"\"},\n  {\"id\": \"b\", \"type\": \"group\", \"x\": -0.5, \"y\": 0, \"width\": 10, \"height\": 10}\n ],\n \"edges\": [{\"id\": \"e\", \"fromNode\": \"a\", \"toNode\": \"b\"}]}";
+
+    #[test]
+    fn read_and_write() {
+        let canvas: JsonCanvas = SOURCE.parse().unwrap();
+        let items: Vec<CanvasItem> = CanvasReader::new(SOURCE.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(items.len(), 3);
+
+        for options in [FormatOptions::default(), FormatOptions::obsidian()] {
+            let mut writer = CanvasWriter::with_options(Vec::new(), options);
+            for item in &items {
+                match item {
+                    CanvasItem::Node(node) => writer.write_node(node).unwrap(),
+                    CanvasItem::Edge(edge) => writer.write_edge(edge).unwrap(),
+                }
+            }
+            let written = String::from_utf8(writer.finish().unwrap()).unwrap();
+            assert_eq!(written, canvas.to_string_with(&options));
+        }
+        let empty = CanvasWriter::new(Vec::new()).finish().unwrap();
+        assert_eq!(empty, b"{}");
+
+        let mut writer = CanvasWriter::new(Vec::new());
+        let CanvasItem::Edge(edge) = &items[2] else {
+            unreachable!()
+        };
+        writer.write_edge(edge).unwrap();
+        let CanvasItem::Node(node) = &items[0] else {
+            unreachable!()
+        };
+        assert!(matches!(
+            writer.write_node(node),
+            Err(JsonCanvasError::NodeAfterEdges)
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let source = SOURCE.replace(
+            "\"y\": 0, \"width\": 10, \"height\": 10}",
+            "\"y\": 0, \"width\": 10, \"height\": 10, \"color\": 7}",
+        );
+        let mut reader = CanvasReader::new(source.as_bytes());
+        assert!(reader.next().unwrap().is_ok());
+        let Some(Err(JsonCanvasError::ParseError(err))) = reader.next() else {
+            panic!("expected an error");
+        };
+        assert_eq!(
+            err.to_string(),
+            "nodes[1].color: invalid color, expected a preset from \"1\" to \"6\" or a hex color, found 7 (id b, line 4, column 87)"
+        );
+        assert!(reader.next().is_none());
+
+        let mut reader = CanvasReader::new("{\"nodes\": [{\"id\": \"a\"".as_bytes());
+        let Some(Err(err)) = reader.next() else {
+            panic!("expected an error");
+        };
+        assert_eq!(
+            err.to_string(),
+            "unexpected end of file (line 1, column 22)"
+        );
+        let mut reader = CanvasReader::new("{\"nodes\": {}}".as_bytes());
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "nodes: expected `[`, found `{` (line 1, column 11)"
+        );
+        // Columns are counted in bytes, like in the errors of serde_json
+        let mut reader = CanvasReader::new("{\"é\": 1, \"nodes\": {}}".as_bytes());
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "nodes: expected `[`, found `{` (line 1, column 20)"
+        );
+
+        // Unknown members are skipped, but must still be well-formed
+        let mut reader = CanvasReader::new("{\"x\": {]}, \"nodes\": []}".as_bytes());
+        assert_eq!(
+            reader.next().unwrap().unwrap_err().to_string(),
+            "expected `}`, found `]` (line 1, column 8)"
+        );
+    }
+}
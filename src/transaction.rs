@@ -0,0 +1,314 @@
+use crate::edge::Edge;
+use crate::history::{update_edge, update_node, Command};
+use crate::node::Node;
+use crate::patch::CanvasPatch;
+use crate::{EdgeId, JsonCanvas, JsonCanvasError, NodeId};
+
+/// Edits made through [`JsonCanvas::transaction`].
+///
+/// Every successful step is journaled so it can be rolled back. A failing step
+/// returns [`JsonCanvasError::TransactionFailed`] with its index, counted from 0.
+pub struct Transaction<'a> {
+    canvas: &'a mut JsonCanvas,
+    journal: CanvasPatch,
+    step: usize,
+}
+
+impl Transaction<'_> {
+    /// The canvas, with the steps done so far applied
+    pub fn canvas(&self) -> &JsonCanvas {
+        self.canvas
+    }
+
+    /// Number of steps done so far
+    pub fn steps(&self) -> usize {
+        self.step
+    }
+
+    pub fn add_node(&mut self, node: Node) -> Result<(), JsonCanvasError> {
+        self.run(|canvas| Command::AddNode(node).run(canvas))
+    }
+
+    pub fn add_edge(&mut self, edge: Edge) -> Result<(), JsonCanvasError> {
+        self.run(|canvas| Command::AddEdge(edge).run(canvas))
+    }
+
+    /// Removes a node along with its edges
+    pub fn remove_node(&mut self, id: &NodeId) -> Result<(), JsonCanvasError> {
+        self.run(|canvas| Command::RemoveNode(id.clone()).run(canvas))
+    }
+
+    pub fn remove_edge(&mut self, id: &EdgeId) -> Result<(), JsonCanvasError> {
+        self.run(|canvas| Command::RemoveEdge(id.clone()).run(canvas))
+    }
+
+    pub fn update_node(
+        &mut self,
+        id: &NodeId,
+        update: impl FnOnce(&mut Node),
+    ) -> Result<(), JsonCanvasError> {
+        self.run(|canvas| {
+            let patch = update_node(canvas, id, update)?;
+            canvas.apply(&patch)?;
+            Ok(patch)
+        })
+    }
+
+    pub fn update_edge(
+        &mut self,
+        id: &EdgeId,
+        update: impl FnOnce(&mut Edge),
+    ) -> Result<(), JsonCanvasError> {
+        self.run(|canvas| {
+            let patch = update_edge(canvas, id, update)?;
+            canvas.apply(&patch)?;
+            Ok(patch)
+        })
+    }
+
+    fn run(
+        &mut self,
+        step: impl FnOnce(&mut JsonCanvas) -> Result<CanvasPatch, JsonCanvasError>,
+    ) -> Result<(), JsonCanvasError> {
+        match step(self.canvas) {
+            Ok(patch) => {
+                patch
+                    .ops()
+                    .iter()
+                    .for_each(|op| self.journal.push(op.clone()));
+                self.step += 1;
+                Ok(())
+            }
+            Err(source) => Err(JsonCanvasError::TransactionFailed {
+                step: self.step,
+                source: Box::new(source),
+            }),
+        }
+    }
+}
+
+impl JsonCanvas {
+    /// Runs `edit` on the canvas, keeping all of its changes or none of them.
+    ///
+    /// If `edit` returns an error, every step done so far is rolled back and the
+    /// error is returned.
+    ///
+    /// ```
+    /// use jsoncanvas::{JsonCanvas, JsonCanvasError, TextNode};
+    ///
+    /// let mut canvas = JsonCanvas::default();
+    /// let result = canvas.transaction(|tx| {
+    ///     tx.add_node(TextNode::new("a".parse().unwrap(), 0, 0, 100, 100, None, "a".to_string()).into())?;
+    ///     tx.remove_node(&"b".parse().unwrap())
+    /// });
+    ///
+    /// assert!(matches!(result, Err(JsonCanvasError::TransactionFailed { step: 1, .. })));
+    /// assert!(canvas.get_nodes().is_empty());
+    /// ```
+    pub fn transaction<T>(
+        &mut self,
+        edit: impl FnOnce(&mut Transaction) -> Result<T, JsonCanvasError>,
+    ) -> Result<T, JsonCanvasError> {
+        self.atomically(|canvas| {
+            let mut transaction = Transaction {
+                canvas,
+                journal: CanvasPatch::default(),
+                step: 0,
+            };
+            match edit(&mut transaction) {
+                Ok(value) => Ok(value),
+                Err(err) => {
+                    let Transaction {
+                        canvas, journal, ..
+                    } = transaction;
+                    canvas
+                        .apply(&journal.invert())
+                        .expect("inverse of an applied patch must apply");
+                    Err(err)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::color::PresetColor;
+    use crate::events::CanvasEvent;
+    use crate::node::{GenericNodeInfo, TextNode};
+
+    fn text(id: &str) -> Node {
+        TextNode::new(id.parse().unwrap(), 0, 0, 100, 100, None, id.to_string()).into()
+    }
+
+    fn edge(id: &str, from: &str, to: &str) -> Edge {
+        Edge::new(
+            id.parse().unwrap(),
+            from.parse().unwrap(),
+            None,
+            None,
+            to.parse().unwrap(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn canvas() -> JsonCanvas {
+        let mut canvas = JsonCanvas::default();
+        for id in ["a", "b", "c"] {
+            canvas.add_node(text(id)).unwrap();
+        }
+        canvas.add_edge(edge("ab", "a", "b")).unwrap();
+        canvas.add_edge(edge("bc", "b", "c")).unwrap();
+        canvas
+    }
+
+    fn order(canvas: &JsonCanvas) -> (Vec<&str>, Vec<&str>) {
+        (
+            canvas.nodes().map(|node| node.id().as_str()).collect(),
+            canvas.edges().map(|edge| edge.id().as_str()).collect(),
+        )
+    }
+
+    fn record(canvas: &mut JsonCanvas) -> Arc<Mutex<Vec<CanvasEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        canvas.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+        events
+    }
+
+    #[test]
+    fn commit() {
+        let mut canvas = canvas();
+        let events = record(&mut canvas);
+
+        let steps = canvas
+            .transaction(|tx| {
+                tx.remove_edge(&"bc".parse().unwrap())?;
+                tx.add_node(text("d"))?;
+                tx.add_edge(edge("cd", "c", "d"))?;
+                tx.update_node(&"a".parse().unwrap(), |node| node.set_x(10))?;
+                assert!(tx.canvas().get_nodes().contains_key(&"d".parse().unwrap()));
+                Ok(tx.steps())
+            })
+            .unwrap();
+
+        assert_eq!(steps, 4);
+        assert_eq!(order(&canvas), (vec!["a", "b", "c", "d"], vec!["ab", "cd"]));
+        assert_eq!(canvas.node(&"a".parse().unwrap()).unwrap().get_x(), 10);
+        assert_eq!(events.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn failing_step_rolls_back() {
+        let mut canvas = canvas();
+        let before = canvas.clone();
+        let events = record(&mut canvas);
+
+        let result = canvas.transaction(|tx| {
+            tx.remove_node(&"b".parse().unwrap())?;
+            tx.update_edge(&"bc".parse().unwrap(), |edge| {
+                edge.set_color(PresetColor::Red.into());
+            })
+        });
+
+        // The edge was removed along with its node by step 0
+        let Err(JsonCanvasError::TransactionFailed { step, source }) = result else {
+            panic!("expected a failed transaction");
+        };
+        assert_eq!(step, 1);
+        assert!(matches!(*source, JsonCanvasError::EdgeNotExists(_)));
+        assert_eq!(canvas, before);
+        assert_eq!(order(&canvas), order(&before));
+        assert!(events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn closure_error_rolls_back() {
+        let mut canvas = canvas();
+        let before = canvas.clone();
+
+        // Errors not coming from a step are returned as they are
+        let result: Result<(), _> = canvas.transaction(|tx| {
+            tx.add_node(text("d"))?;
+            tx.update_node(&"d".parse().unwrap(), |node| node.set_x(50))?;
+            if tx.canvas().get_nodes().len() > 3 {
+                return Err(JsonCanvasError::NodeExists("d".parse().unwrap()));
+            }
+            Ok(())
+        });
+
+        assert!(matches!(result, Err(JsonCanvasError::NodeExists(_))));
+        assert_eq!(canvas, before);
+    }
+
+    #[test]
+    fn works_with_history() {
+        let mut canvas = canvas();
+        let before = canvas.clone();
+        let mut history = crate::history::History::new();
+
+        canvas
+            .transaction(|tx| {
+                tx.add_node(text("d"))?;
+                tx.add_edge(edge("ad", "a", "d"))
+            })
+            .unwrap();
+        let after = canvas.clone();
+        history
+            .execute(
+                &mut canvas,
+                Command::Batch(vec![Command::RemoveNode("d".parse().unwrap())]),
+            )
+            .unwrap();
+        history.undo(&mut canvas).unwrap();
+        assert_eq!(canvas, after);
+
+        let result = canvas.transaction(|tx| {
+            tx.remove_node(&"d".parse().unwrap())?;
+            tx.remove_node(&"d".parse().unwrap())
+        });
+        assert!(matches!(
+            result,
+            Err(JsonCanvasError::TransactionFailed { step: 1, .. })
+        ));
+        assert_eq!(canvas, after);
+        canvas.remove_node(&"d".parse().unwrap()).unwrap();
+        assert_eq!(canvas, before);
+    }
+
+    /// The journal only holds the patches of the steps that were applied, with the
+    /// indices the items had, and nothing else can change the canvas while it is
+    /// borrowed by the transaction: its inverse always applies, even after steps
+    /// removing connected edges and restoring them in place
+    #[test]
+    fn rollback_restores_every_step() {
+        let mut canvas = canvas();
+        canvas.move_node(&"c".parse().unwrap(), 0).unwrap();
+        let before = canvas.clone();
+
+        let result = canvas.transaction(|tx| {
+            tx.update_edge(&"ab".parse().unwrap(), |edge| {
+                edge.set_to("c".parse().unwrap(), None, None);
+            })?;
+            tx.remove_node(&"b".parse().unwrap())?;
+            tx.remove_edge(&"ab".parse().unwrap())?;
+            tx.add_node(text("b"))?;
+            tx.update_node(&"a".parse().unwrap(), |node| node.set_y(30))?;
+            tx.remove_node(&"a".parse().unwrap())?;
+            tx.add_edge(edge("ab", "a", "b"))
+        });
+
+        assert!(matches!(
+            result,
+            Err(JsonCanvasError::TransactionFailed { step: 6, .. })
+        ));
+        assert_eq!(canvas, before);
+        assert_eq!(order(&canvas), order(&before));
+    }
+}
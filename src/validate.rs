@@ -0,0 +1,231 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::parse::{type_fields, Expect, EDGE_FIELDS, NODE_FIELDS};
+use crate::{JsonCanvas, JsonCanvasError};
+
+/// Rule of the JSON Canvas 1.0 specification broken by a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    /// JSON path of the invalid value, like `nodes[42].color`, or empty for the whole
+    /// document
+    pub path: String,
+    /// ID of the node or edge holding the invalid value, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+const SIDES: &[&str] = &["top", "right", "bottom", "left"];
+const ENDS: &[&str] = &["none", "arrow"];
+const BACKGROUND_STYLES: &[&str] = &["cover", "ratio", "repeat"];
+const NODE_TYPES: &[&str] = &["text", "file", "link", "group"];
+
+/// Checks a JSON document against every rule of the JSON Canvas 1.0 specification.
+///
+/// Unlike parsing, this rejects float coordinates, negative sizes, malformed hex
+/// colors, duplicate IDs and edges to missing nodes. Unknown fields are allowed, as
+/// the specification does not forbid them.
+pub fn validate_value(value: &Value) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let Value::Object(root) = value else {
+        violations.push(Violation {
+            path: String::new(),
+            id: None,
+            message: format!("expected an object, found {}", value),
+        });
+        return violations;
+    };
+
+    let mut node_ids = HashSet::new();
+    let mut edge_ids = HashSet::new();
+    for key in ["nodes", "edges"] {
+        let Some(items) = root.get(key) else {
+            continue;
+        };
+        let Value::Array(items) = items else {
+            violations.push(Violation {
+                path: key.to_string(),
+                id: None,
+                message: format!("expected an array, found {}", items),
+            });
+            continue;
+        };
+        for (index, item) in items.iter().enumerate() {
+            let path = format!("{}[{}]", key, index);
+            let mut violation = |field: Option<&str>, message: String| {
+                violations.push(Violation {
+                    path: match field {
+                        Some(field) => format!("{}.{}", path, field),
+                        None => path.clone(),
+                    },
+                    id: item.get("id").and_then(Value::as_str).map(str::to_string),
+                    message,
+                })
+            };
+            let Value::Object(fields) = item else {
+                violation(None, format!("expected an object, found {}", item));
+                continue;
+            };
+
+            if key == "nodes" {
+                check_fields(fields, &NODE_FIELDS, &mut violation);
+                match fields.get("type") {
+                    None => violation(None, "missing field `type`".to_string()),
+                    Some(node_type) => match node_type.as_str().and_then(type_fields) {
+                        Some(specific) => check_fields(fields, specific, &mut violation),
+                        None => violation(
+                            Some("type"),
+                            format!(
+                                "expected one of {}, found {}",
+                                NODE_TYPES.join(", "),
+                                node_type
+                            ),
+                        ),
+                    },
+                }
+                if let Some(id) = fields.get("id").and_then(Value::as_str) {
+                    if !node_ids.insert(id) {
+                        violation(Some("id"), format!("duplicate node ID \"{}\"", id));
+                    }
+                }
+            } else {
+                check_fields(fields, &EDGE_FIELDS, &mut violation);
+                if let Some(id) = fields.get("id").and_then(Value::as_str) {
+                    if !edge_ids.insert(id) {
+                        violation(Some("id"), format!("duplicate edge ID \"{}\"", id));
+                    }
+                }
+                for end in ["fromNode", "toNode"] {
+                    if let Some(node) = fields.get(end).and_then(Value::as_str) {
+                        if !node_ids.contains(node) {
+                            violation(Some(end), format!("node \"{}\" does not exist", node));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// Same as [`validate_value`], for a document which may not be valid JSON
+pub fn validate_str(s: &str) -> Result<Vec<Violation>, JsonCanvasError> {
+    let value: Value = serde_json::from_str(s)?;
+    Ok(validate_value(&value))
+}
+
+impl JsonCanvas {
+    /// Checks the canvas, as it would be written, against the JSON Canvas 1.0
+    /// specification. See [`validate_value`].
+    pub fn validate(&self) -> Vec<Violation> {
+        validate_value(&serde_json::to_value(self).expect("a canvas is valid JSON"))
+    }
+}
+
+fn check_fields(
+    fields: &Map<String, Value>,
+    expected: &[(&str, bool, Expect)],
+    violation: &mut impl FnMut(Option<&str>, String),
+) {
+    for (name, required, expect) in expected {
+        match fields.get(*name) {
+            None if *required => violation(None, format!("missing field `{}`", name)),
+            None => {}
+            Some(value) => {
+                if let Err(message) = check(value, *expect) {
+                    violation(Some(name), message);
+                }
+            }
+        }
+    }
+}
+
+/// Checks a value as the specification requires, more strictly than parsing
+fn check(value: &Value, expect: Expect) -> Result<(), String> {
+    let s = value.as_str();
+    let one_of = |values: &[&str]| s.is_some_and(|s| values.contains(&s));
+    let valid = match expect {
+        // The specification does not require a valid URL
+        Expect::String | Expect::Url => s.is_some(),
+        Expect::Integer => value.is_i64(),
+        Expect::Dimension => value.is_u64(),
+        Expect::Color => s.is_some_and(|s| {
+            matches!(s, "1" | "2" | "3" | "4" | "5" | "6")
+                || (s.len() == 7
+                    && s.starts_with('#')
+                    && s[1..].chars().all(|c| c.is_ascii_hexdigit()))
+        }),
+        Expect::Subpath => s.is_some_and(|s| s.starts_with('#')),
+        Expect::Side => one_of(SIDES),
+        Expect::End => one_of(ENDS),
+        Expect::BackgroundStyle => one_of(BACKGROUND_STYLES),
+    };
+    if valid {
+        return Ok(());
+    }
+    let expected = match expect {
+        Expect::String | Expect::Url => "expected a string".to_string(),
+        Expect::Integer => "expected an integer".to_string(),
+        Expect::Dimension => "expected a non-negative integer".to_string(),
+        Expect::Color => {
+            "expected a preset color from \"1\" to \"6\" or a hex color like \"#FF0000\""
+                .to_string()
+        }
+        Expect::Subpath => "expected a string starting with #".to_string(),
+        Expect::Side => format!("expected one of {}", SIDES.join(", ")),
+        Expect::End => format!("expected one of {}", ENDS.join(", ")),
+        Expect::BackgroundStyle => format!("expected one of {}", BACKGROUND_STYLES.join(", ")),
+    };
+    Err(format!("{}, found {}", expected, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_violation_is_reported() {
+        let violations = validate_str(
+            r##"{"nodes": [
+                {"id": "a", "type": "file", "x": 0.5, "y": 0, "width": 10, "height": -1,
+                 "file": "a.md", "subpath": "heading", "color": "7"},
+                {"id": "a", "type": "group", "x": 0, "y": 0, "width": 10, "height": 10,
+                 "backgroundStyle": "stretch"}
+            ], "edges": [
+                {"id": "e", "fromNode": "a", "toNode": "b", "toEnd": "circle"}
+            ]}"##,
+        )
+        .unwrap();
+        let paths: Vec<&str> = violations.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "nodes[0].x",
+                "nodes[0].height",
+                "nodes[0].color",
+                "nodes[0].subpath",
+                "nodes[1].backgroundStyle",
+                "nodes[1].id",
+                "edges[0].toEnd",
+                "edges[0].toNode",
+            ]
+        );
+        assert_eq!(
+            violations[6].to_string(),
+            "edges[0].toEnd: expected one of none, arrow, found \"circle\""
+        );
+    }
+}
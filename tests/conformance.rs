@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::Path;
+
+use jsoncanvas::format::FormatOptions;
+use jsoncanvas::schema::validate_schema;
+use jsoncanvas::validate::validate_str;
+use jsoncanvas::JsonCanvas;
+
+/// Path of the first violation reported for each invalid fixture, `None` for a
+/// document which is not valid JSON
+const INVALID: &[(&str, Option<&str>)] = &[
+    ("dangling-edge.canvas", Some("edges[0].toNode")),
+    ("duplicate-edge-id.canvas", Some("edges[1].id")),
+    ("duplicate-node-id.canvas", Some("nodes[1].id")),
+    ("float-coordinate.canvas", Some("nodes[0].x")),
+    ("missing-file.canvas", Some("nodes[0]")),
+    ("missing-id.canvas", Some("nodes[0]")),
+    ("missing-position.canvas", Some("nodes[0]")),
+    ("missing-text.canvas", Some("nodes[0]")),
+    ("missing-to-node.canvas", Some("edges[0]")),
+    ("missing-type.canvas", Some("nodes[0]")),
+    ("missing-url.canvas", Some("nodes[0]")),
+    ("negative-size.canvas", Some("nodes[0].width")),
+    ("node-not-an-object.canvas", Some("nodes[0]")),
+    ("nodes-not-an-array.canvas", Some("nodes")),
+    ("non-string-label.canvas", Some("edges[0].label")),
+    ("not-an-object.canvas", Some("")),
+    ("numeric-color.canvas", Some("nodes[0].color")),
+    ("preset-color-out-of-range.canvas", Some("nodes[0].color")),
+    ("short-hex-color.canvas", Some("nodes[0].color")),
+    ("string-size.canvas", Some("nodes[0].height")),
+    ("subpath-without-hash.canvas", Some("nodes[0].subpath")),
+    ("syntax-error.canvas", None),
+    (
+        "unknown-background-style.canvas",
+        Some("nodes[0].backgroundStyle"),
+    ),
+    ("unknown-end.canvas", Some("edges[0].toEnd")),
+    ("unknown-side.canvas", Some("edges[0].fromSide")),
+    ("unknown-type.canvas", Some("nodes[0].type")),
+];
+
+fn fixtures(kind: &str) -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(kind);
+    let mut fixtures: Vec<(String, String)> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            (name, fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+#[test]
+fn valid_fixtures() {
+    for (name, source) in fixtures("valid") {
+        let violations = validate_str(&source).unwrap();
+        assert!(violations.is_empty(), "{}: {:?}", name, violations);
+        let errors = validate_schema(&serde_json::from_str(&source).unwrap());
+        assert!(errors.is_empty(), "{}: {:?}", name, errors);
+
+        let canvas: JsonCanvas = source
+            .parse()
+            .unwrap_or_else(|err| panic!("{}: {}", name, err));
+        assert!(canvas.validate().is_empty(), "{} written back", name);
+        assert_eq!(canvas.to_string().parse::<JsonCanvas>().unwrap(), canvas);
+        if name.starts_with("obsidian") {
+            assert_eq!(canvas.to_string_with(&FormatOptions::obsidian()), source);
+        }
+    }
+}
+
+#[test]
+fn invalid_fixtures() {
+    let fixtures = fixtures("invalid");
+    let names: Vec<&str> = fixtures.iter().map(|(name, _)| name.as_str()).collect();
+    let expected: Vec<&str> = INVALID.iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        names, expected,
+        "every invalid fixture needs an expectation"
+    );
+
+    for ((name, source), (_, path)) in fixtures.iter().zip(INVALID) {
+        match (validate_str(source), path) {
+            (Err(_), None) => {}
+            (Ok(violations), Some(path)) => {
+                let first = violations.first().map(|violation| violation.path.as_str());
+                assert_eq!(first, Some(*path), "{}", name);
+            }
+            (result, _) => panic!("{}: unexpected {:?}", name, result),
+        }
+    }
+}
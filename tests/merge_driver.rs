@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use jsoncanvas::node::GenericNodeInfo;
+use jsoncanvas::JsonCanvas;
+
+const BASE: &str =
+    r#"{"nodes":[{"type":"text","id":"a","x":0,"y":0,"width":10,"height":10,"text":"A"}]}"#;
+
+/// Empty directory for the files of one test
+fn directory(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "jsoncanvas-merge-driver-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes the three sides and runs the driver on them with `options`
+fn merge(dir: &Path, ours: &str, theirs: &str, options: &[&str]) -> Output {
+    let files = [("base", BASE), ("ours", ours), ("theirs", theirs)];
+    for (name, content) in files {
+        fs::write(dir.join(name), content).unwrap();
+    }
+    Command::new(env!("CARGO_BIN_EXE_canvas-merge-driver"))
+        .args(options)
+        .args(files.map(|(name, _)| dir.join(name)))
+        .output()
+        .unwrap()
+}
+
+fn moved(x: i64) -> String {
+    BASE.replace(r#""x":0"#, &format!(r#""x":{}"#, x))
+}
+
+#[test]
+fn clean_merge() {
+    let dir = directory("clean");
+    let theirs = BASE.replace(r#""text":"A""#, r#""text":"B""#);
+    let output = merge(&dir, &moved(50), &theirs, &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let merged = JsonCanvas::load(dir.join("ours")).unwrap();
+    let node = merged.nodes().next().unwrap();
+    assert_eq!(node.get_x(), 50);
+    assert_eq!(
+        merged.to_string(),
+        moved(50).replace(r#""text":"A""#, r#""text":"B""#)
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn conflict_writes_report() {
+    let dir = directory("conflict");
+    let report = dir.join("report.json");
+    let output = merge(
+        &dir,
+        &moved(50),
+        &moved(70),
+        &["--policy", "theirs", "--report", report.to_str().unwrap()],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("node a: field x changed on both sides"));
+    let merged = JsonCanvas::load(dir.join("ours")).unwrap();
+    assert_eq!(merged.nodes().next().unwrap().get_x(), 70);
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(report).unwrap()).unwrap();
+    assert_eq!(
+        report,
+        serde_json::json!([
+            {"conflict": "nodeField", "id": "a", "field": "x", "ours": 50, "theirs": 70}
+        ])
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bad_arguments() {
+    let dir = directory("arguments");
+    for options in [
+        &["--polcy", "theirs"][..],
+        &["--policy", "mine"],
+        &["--report"],
+    ] {
+        let output = merge(&dir, BASE, BASE, options);
+        assert_eq!(output.status.code(), Some(2), "{:?}", options);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("usage: canvas-merge-driver"),
+            "{:?}",
+            options
+        );
+    }
+    // The files are left untouched
+    assert_eq!(fs::read_to_string(dir.join("ours")).unwrap(), BASE);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_canvas-merge-driver"))
+        .arg(dir.join("base"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    fs::remove_dir_all(&dir).unwrap();
+}